@@ -0,0 +1,302 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::results::{LatencySummary, Operation, RunResults};
+
+/// A threshold a run has to satisfy, e.g. `p99<200ms`, `error_rate<0.1%`,
+/// `rps>500` or `create_wish:p50<=20ms` for a single operation.
+#[derive(Clone, Debug)]
+pub struct Assertion {
+    operation: Option<Operation>,
+    metric: Metric,
+    comparison: Comparison,
+    /// Latencies in ms, error rates as fraction, throughput in req/s.
+    threshold: f64,
+    text: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Metric {
+    Min,
+    Mean,
+    P50,
+    P90,
+    P95,
+    P99,
+    Max,
+    ErrorRate,
+    Rps,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+/// The result of checking one assertion against a run.
+#[derive(Clone, Debug)]
+pub struct AssertionOutcome {
+    pub assertion: Assertion,
+    /// The measured value, `None` if the run has no requests for the operation.
+    pub actual: Option<f64>,
+}
+
+impl Assertion {
+    pub fn check(&self, results: &RunResults) -> AssertionOutcome {
+        let actual = match self.operation {
+            Some(operation) => results
+                .operations
+                .iter()
+                .find(|op| op.operation == operation)
+                .map(|op| (op.count, op.errors, op.throughput, &op.latency)),
+            None => Some((
+                results.overall.count,
+                results.overall.errors,
+                results.overall.throughput,
+                &results.overall.latency,
+            )),
+        }
+        .filter(|(count, ..)| *count > 0)
+        .map(|(count, errors, throughput, latency)| match self.metric {
+            Metric::ErrorRate => errors as f64 / count as f64,
+            Metric::Rps => throughput,
+            latency_metric => latency_value(latency_metric, latency),
+        });
+        AssertionOutcome {
+            assertion: self.clone(),
+            actual,
+        }
+    }
+}
+
+fn latency_value(metric: Metric, latency: &LatencySummary) -> f64 {
+    match metric {
+        Metric::Min => latency.min_ms,
+        Metric::Mean => latency.mean_ms,
+        Metric::P50 => latency.p50_ms,
+        Metric::P90 => latency.p90_ms,
+        Metric::P95 => latency.p95_ms,
+        Metric::P99 => latency.p99_ms,
+        Metric::Max => latency.max_ms,
+        Metric::ErrorRate | Metric::Rps => unreachable!("not a latency metric"),
+    }
+}
+
+impl AssertionOutcome {
+    pub fn passed(&self) -> bool {
+        let Some(actual) = self.actual else {
+            return false;
+        };
+        let threshold = self.assertion.threshold;
+        match self.assertion.comparison {
+            Comparison::Less => actual < threshold,
+            Comparison::LessOrEqual => actual <= threshold,
+            Comparison::Greater => actual > threshold,
+            Comparison::GreaterOrEqual => actual >= threshold,
+        }
+    }
+
+    /// Human readable measured value, in the unit of the assertion.
+    pub fn actual_text(&self) -> String {
+        match self.actual {
+            None => "no requests".to_owned(),
+            Some(actual) => match self.assertion.metric {
+                Metric::ErrorRate => format!("{:.3}%", actual * 100.0),
+                Metric::Rps => format!("{:.1} req/s", actual),
+                _ => format!("{:.2}ms", actual),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Assertion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+impl FromStr for Assertion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let text: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+        let (operation, rest) = match text.split_once(':') {
+            Some((operation, rest)) => (Some(parse_operation(operation)?), rest),
+            None => (None, text.as_str()),
+        };
+
+        let position = rest
+            .find(['<', '>'])
+            .ok_or_else(|| format!("`{}` has no comparison, use one of <, <=, >, >=", s))?;
+        let (metric, rest) = rest.split_at(position);
+        let (comparison, value) = if let Some(value) = rest.strip_prefix("<=") {
+            (Comparison::LessOrEqual, value)
+        } else if let Some(value) = rest.strip_prefix(">=") {
+            (Comparison::GreaterOrEqual, value)
+        } else if let Some(value) = rest.strip_prefix('<') {
+            (Comparison::Less, value)
+        } else {
+            (Comparison::Greater, &rest[1..])
+        };
+
+        let metric = match metric {
+            "min" => Metric::Min,
+            "mean" | "avg" => Metric::Mean,
+            "p50" | "median" => Metric::P50,
+            "p90" => Metric::P90,
+            "p95" => Metric::P95,
+            "p99" => Metric::P99,
+            "max" => Metric::Max,
+            "error_rate" => Metric::ErrorRate,
+            "rps" => Metric::Rps,
+            other => {
+                return Err(format!(
+                    "unknown metric `{}`, use one of min, mean, p50, p90, p95, p99, max, error_rate, rps",
+                    other
+                ))
+            }
+        };
+        let threshold = parse_threshold(metric, value)
+            .ok_or_else(|| format!("invalid threshold `{}` for `{}`", value, s))?;
+
+        Ok(Self {
+            operation,
+            metric,
+            comparison,
+            threshold,
+            text,
+        })
+    }
+}
+
+pub fn parse_operation(name: &str) -> Result<Operation, String> {
+    match name {
+        "register" => Ok(Operation::Register),
+        "login" => Ok(Operation::Login),
+        "create_wish" => Ok(Operation::CreateWish),
+        "get_wishes" => Ok(Operation::GetWishes),
+        other => Err(format!(
+            "unknown operation `{}`, use one of register, login, create_wish, get_wishes",
+            other
+        )),
+    }
+}
+
+/// Parse a rate given as percentage (`0.1%`) or fraction (`0.001`), from 0 to 100 %.
+pub fn parse_rate(value: &str) -> Result<f64, String> {
+    match value.strip_suffix('%') {
+        Some(percent) => percent.parse::<f64>().map(|p| p / 100.0),
+        None => value.parse(),
+    }
+    .ok()
+    .filter(|rate| (0.0..=1.0).contains(rate))
+    .ok_or_else(|| {
+        format!(
+            "invalid rate `{}`, use e.g. 0.1% or 0.001, from 0 to 100%",
+            value
+        )
+    })
+}
+
+fn parse_threshold(metric: Metric, value: &str) -> Option<f64> {
+    match metric {
+        Metric::ErrorRate => parse_rate(value).ok(),
+        Metric::Rps => value.parse().ok(),
+        _ => {
+            let (number, factor) = if let Some(number) = value.strip_suffix("ms") {
+                (number, 1.0)
+            } else if let Some(number) = value.strip_suffix("us") {
+                (number, 0.001)
+            } else if let Some(number) = value.strip_suffix('s') {
+                (number, 1000.0)
+            } else {
+                (value, 1.0)
+            };
+            number.parse::<f64>().ok().map(|n| n * factor)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Assertion {
+        s.parse().unwrap_or_else(|err| panic!("`{}`: {}", s, err))
+    }
+
+    #[test]
+    fn parses_latency_thresholds() {
+        let assertion = parse("p99<200ms");
+        assert_eq!(assertion.operation, None);
+        assert_eq!(assertion.metric, Metric::P99);
+        assert_eq!(assertion.comparison, Comparison::Less);
+        assert_eq!(assertion.threshold, 200.0);
+        assert_eq!(parse("max<=1.5s").threshold, 1500.0);
+        assert_eq!(parse("mean<500us").threshold, 0.5);
+        assert_eq!(parse("median<20").threshold, 20.0);
+        assert_eq!(parse("avg>1ms").metric, Metric::Mean);
+    }
+
+    #[test]
+    fn parses_comparisons() {
+        assert_eq!(parse("p50<1ms").comparison, Comparison::Less);
+        assert_eq!(parse("p50<=1ms").comparison, Comparison::LessOrEqual);
+        assert_eq!(parse("p50>1ms").comparison, Comparison::Greater);
+        assert_eq!(parse("p50>=1ms").comparison, Comparison::GreaterOrEqual);
+    }
+
+    #[test]
+    fn parses_rates_and_throughput() {
+        let assertion = parse("error_rate<0.1%");
+        assert_eq!(assertion.metric, Metric::ErrorRate);
+        assert!((assertion.threshold - 0.001).abs() < 1e-12);
+        assert_eq!(parse("error_rate<=0.05").threshold, 0.05);
+        let assertion = parse("rps>500");
+        assert_eq!(assertion.metric, Metric::Rps);
+        assert_eq!(assertion.threshold, 500.0);
+    }
+
+    #[test]
+    fn parses_operations_and_ignores_whitespace() {
+        let assertion = parse(" create_wish : p50 <= 20 ms ");
+        assert_eq!(assertion.operation, Some(Operation::CreateWish));
+        assert_eq!(assertion.metric, Metric::P50);
+        assert_eq!(assertion.comparison, Comparison::LessOrEqual);
+        assert_eq!(assertion.threshold, 20.0);
+        assert_eq!(assertion.to_string(), "create_wish:p50<=20ms");
+        assert_eq!(parse("login:rps>1").operation, Some(Operation::Login));
+    }
+
+    #[test]
+    fn rejects_invalid_assertions() {
+        for invalid in [
+            "p99",
+            "p99=200ms",
+            "p42<200ms",
+            "p99<",
+            "p99<fast",
+            "error_rate<1ms",
+            "delete_wish:p99<200ms",
+            "<200ms",
+        ] {
+            assert!(invalid.parse::<Assertion>().is_err(), "`{}`", invalid);
+        }
+    }
+
+    #[test]
+    fn parses_rates() {
+        assert_eq!(parse_rate("5%"), Ok(0.05));
+        assert_eq!(parse_rate("0.5"), Ok(0.5));
+        assert_eq!(parse_rate("0"), Ok(0.0));
+        assert_eq!(parse_rate("100%"), Ok(1.0));
+        for invalid in [
+            "five", "-1%", "-0.1", "101%", "1.5", "NaN", "nan%", "inf", "",
+        ] {
+            assert!(parse_rate(invalid).is_err(), "`{}`", invalid);
+        }
+    }
+}
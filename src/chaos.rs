@@ -0,0 +1,401 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::error::Error;
+use crate::playground::{Component, Topology};
+use crate::results::{as_ms, Overall, Recorder, Sample};
+use crate::run_dir::RunDir;
+
+/// How often the schedule is checked.
+const TICK: Duration = Duration::from_millis(50);
+
+/// A fault injected into a playground component during the measured phase,
+/// e.g. `pause:tikv.1@10s+5s` or `kill:pd@30s+20s`.
+#[derive(Clone, Debug)]
+pub struct Fault {
+    action: Action,
+    /// `pd` or `tikv`.
+    role: String,
+    /// Which of the nodes of the role, counted from 0.
+    index: usize,
+    /// Offset from the start of the measured phase.
+    at: Duration,
+    /// How long until the component is resumed or restarted, `None` for the rest of the run.
+    duration: Option<Duration>,
+    text: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Action {
+    /// SIGSTOP, resumed with SIGCONT.
+    Pause,
+    /// SIGKILL, restarted with the same command line.
+    Kill,
+}
+
+/// What happened around one fault, as stored in the results.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FaultSummary {
+    pub fault: String,
+    /// e.g. `tikv.1 (pid 4242)`
+    pub target: String,
+    /// Offsets from the start of the measured phase, `None` if the run ended before.
+    pub injected_ms: Option<f64>,
+    pub recovered_ms: Option<f64>,
+    /// Requests in a window as long as the fault before it, during it and after it.
+    pub before: Option<Overall>,
+    pub during: Option<Overall>,
+    pub after: Option<Overall>,
+}
+
+/// Runs the faults on a background thread until stopped.
+pub struct Chaos {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<Vec<Injection>>,
+}
+
+/// A fault, its target and when it was injected and recovered.
+struct Injection {
+    fault: Fault,
+    target: Component,
+    /// Offsets from the start of the run, like the samples.
+    injected: Option<Duration>,
+    recovered: Option<Duration>,
+    /// Working directory of the target, where `/proc` tells it.
+    cwd: Option<PathBuf>,
+    /// The component started again after a kill.
+    restarted: Option<Child>,
+}
+
+impl Chaos {
+    /// Check that the targets of `faults` exist and start the schedule.
+    pub fn start(
+        faults: &[Fault],
+        topology: &Topology,
+        recorder: Recorder,
+        run_dir: &RunDir,
+    ) -> Result<Self, Error> {
+        let mut injections = Vec::new();
+        for fault in faults {
+            let target = topology
+                .components
+                .iter()
+                .filter(|component| component.role == fault.role)
+                .nth(fault.index)
+                .ok_or_else(|| {
+                    Error::Invalid(format!(
+                        "cannot inject `{}`, the playground has no {}.{} process",
+                        fault, fault.role, fault.index
+                    ))
+                })?;
+            if fault.action == Action::Kill && target.command.is_empty() {
+                return Err(Error::Invalid(format!(
+                    "cannot inject `{}`, the command line of {}.{} is unknown",
+                    fault, fault.role, fault.index
+                )));
+            }
+            injections.push(Injection {
+                fault: fault.clone(),
+                target: target.clone(),
+                injected: None,
+                recovered: None,
+                cwd: None,
+                restarted: None,
+            });
+        }
+        let logs = run_dir.path().to_path_buf();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::clone(&stop);
+        // the measured phase has started before the faults are scheduled
+        let measure_from = recorder.measure_from();
+        let handle = thread::spawn(move || {
+            loop {
+                let stopping = stopped.load(Ordering::Relaxed);
+                let now = recorder.offset(Instant::now());
+                let phase = now.saturating_sub(measure_from);
+                for injection in &mut injections {
+                    injection.step(phase, now, stopping, &logs);
+                }
+                if stopping {
+                    break;
+                }
+                thread::sleep(TICK);
+            }
+            injections
+        });
+        Ok(Self { stop, handle })
+    }
+
+    /// Recover what is still broken and summarize each fault. Restarted components are
+    /// handed out with the pid they replace, as they have to be stopped with the cluster.
+    pub fn stop(self, recorder: &Recorder) -> (Vec<FaultSummary>, Vec<(u32, Child)>) {
+        self.stop.store(true, Ordering::Relaxed);
+        let injections = self.handle.join().unwrap_or_else(|_| {
+            eprintln!("Chaos schedule panicked!");
+            Vec::new()
+        });
+        let samples = recorder.measured_samples();
+        let measure_from = recorder.measure_from();
+        let end = measure_from + recorder.elapsed();
+        let mut summaries = Vec::new();
+        let mut restarted = Vec::new();
+        for mut injection in injections {
+            summaries.push(injection.summarize(&samples, measure_from, end));
+            let killed = injection.target.pid;
+            restarted.extend(injection.restarted.take().map(|child| (killed, child)));
+        }
+        (summaries, restarted)
+    }
+}
+
+impl Injection {
+    fn step(&mut self, phase: Duration, now: Duration, stopping: bool, logs: &Path) {
+        if self.injected.is_none() {
+            if !stopping && phase >= self.fault.at {
+                self.inject();
+                self.injected = Some(now);
+            }
+            return;
+        }
+        if self.recovered.is_some() {
+            return;
+        }
+        let due = self
+            .fault
+            .duration
+            .is_some_and(|_| phase >= self.fault.end());
+        if due {
+            self.recover(logs);
+            self.recovered = Some(now);
+        } else if stopping && self.fault.action == Action::Pause {
+            // a stopped process would keep the cluster from shutting down
+            self.recover(logs);
+        }
+    }
+
+    fn inject(&mut self) {
+        let signal = match self.fault.action {
+            Action::Pause => "-STOP",
+            Action::Kill => "-KILL",
+        };
+        println!("Chaos: {} {}.", self.fault, self.target_name());
+        self.cwd = std::fs::read_link(format!("/proc/{}/cwd", self.target.pid)).ok();
+        signal_process(signal, self.target.pid);
+    }
+
+    fn recover(&mut self, logs: &Path) {
+        match self.fault.action {
+            Action::Pause => {
+                println!("Chaos: resuming {}.", self.target_name());
+                signal_process("-CONT", self.target.pid);
+            }
+            Action::Kill => {
+                println!("Chaos: restarting {}.", self.target_name());
+                let log = logs.join(format!(
+                    "{}.{}-restart.log",
+                    self.fault.role, self.fault.index
+                ));
+                let started = std::fs::File::create(&log).and_then(|log| {
+                    let mut command = Command::new(&self.target.command[0]);
+                    if let Some(cwd) = &self.cwd {
+                        command.current_dir(cwd);
+                    }
+                    command
+                        .args(&self.target.command[1..])
+                        .stdout(log.try_clone()?)
+                        .stderr(log)
+                        .spawn()
+                });
+                match started {
+                    Ok(child) => self.restarted = Some(child),
+                    Err(err) => eprintln!("Failed to restart {}: {}", self.target_name(), err),
+                }
+            }
+        }
+    }
+
+    fn target_name(&self) -> String {
+        format!(
+            "{}.{} (pid {})",
+            self.fault.role, self.fault.index, self.target.pid
+        )
+    }
+
+    fn summarize(&self, samples: &[Sample], measure_from: Duration, end: Duration) -> FaultSummary {
+        let window = |from: Duration, until: Duration| {
+            let samples: Vec<&Sample> = samples
+                .iter()
+                .filter(|sample| sample.start >= from && sample.start < until)
+                .collect();
+            Overall::new(&samples, until.saturating_sub(from))
+        };
+        let (mut before, mut during, mut after) = (None, None, None);
+        if let Some(injected) = self.injected {
+            let until = self.recovered.unwrap_or(end).max(injected);
+            let length = until - injected;
+            before = Some(window(
+                injected.saturating_sub(length).max(measure_from),
+                injected,
+            ));
+            during = Some(window(injected, until));
+            after = self
+                .recovered
+                .map(|recovered| window(recovered, (recovered + length).min(end)));
+        }
+        let offset = |at: Option<Duration>| at.map(|at| as_ms(at.saturating_sub(measure_from)));
+        FaultSummary {
+            fault: self.fault.to_string(),
+            target: self.target_name(),
+            injected_ms: offset(self.injected),
+            recovered_ms: offset(self.recovered),
+            before,
+            during,
+            after,
+        }
+    }
+}
+
+fn signal_process(signal: &str, pid: u32) {
+    if let Err(err) = Command::new("kill")
+        .arg(signal)
+        .arg(pid.to_string())
+        .status()
+    {
+        eprintln!("Failed to send {} to {}: {}", signal, pid, err);
+    }
+}
+
+impl Fault {
+    /// Offset from the start of the measured phase at which the fault is over.
+    pub fn end(&self) -> Duration {
+        self.at.saturating_add(self.duration.unwrap_or_default())
+    }
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+impl FromStr for Fault {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let text: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+        let invalid = || {
+            format!(
+                "invalid fault `{}`, use `<pause|kill>:<pd|tikv>[.<index>]@<at>[+<duration>]`, e.g. `pause:tikv.1@10s+5s`",
+                s
+            )
+        };
+        let (action, rest) = text.split_once(':').ok_or_else(invalid)?;
+        let action = match action {
+            "pause" => Action::Pause,
+            "kill" => Action::Kill,
+            _ => return Err(invalid()),
+        };
+        let (target, schedule) = rest.split_once('@').ok_or_else(invalid)?;
+        let (role, index) = match target.split_once('.') {
+            Some((role, index)) => (role, index.parse().map_err(|_| invalid())?),
+            None => (target, 0),
+        };
+        if role != "pd" && role != "tikv" {
+            return Err(invalid());
+        }
+        let (at, duration) = match schedule.split_once('+') {
+            Some((at, duration)) => (at, Some(parse_duration(duration).ok_or_else(invalid)?)),
+            None => (schedule, None),
+        };
+        Ok(Self {
+            action,
+            role: role.to_owned(),
+            index,
+            at: parse_duration(at).ok_or_else(invalid)?,
+            duration,
+            text,
+        })
+    }
+}
+
+/// Parse `500ms`, `10s` or `10` (seconds).
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let seconds = match value.strip_suffix("ms") {
+        Some(ms) => ms.parse::<f64>().ok()? / 1000.0,
+        None => value.strip_suffix('s').unwrap_or(value).parse().ok()?,
+    };
+    // negative, NaN and too large values are rejected
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Fault {
+        s.parse().unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    #[test]
+    fn parses_faults() {
+        let fault = parse("pause:tikv.1@10s+5s");
+        assert_eq!(fault.action, Action::Pause);
+        assert_eq!(fault.role, "tikv");
+        assert_eq!(fault.index, 1);
+        assert_eq!(fault.at, Duration::from_secs(10));
+        assert_eq!(fault.duration, Some(Duration::from_secs(5)));
+        assert_eq!(fault.end(), Duration::from_secs(15));
+        assert_eq!(fault.to_string(), "pause:tikv.1@10s+5s");
+    }
+
+    #[test]
+    fn defaults_to_the_first_node_for_the_rest_of_the_run() {
+        let fault = parse(" kill : pd @ 500ms ");
+        assert_eq!(fault.action, Action::Kill);
+        assert_eq!(fault.role, "pd");
+        assert_eq!(fault.index, 0);
+        assert_eq!(fault.at, Duration::from_millis(500));
+        assert_eq!(fault.duration, None);
+        assert_eq!(fault.to_string(), "kill:pd@500ms");
+    }
+
+    #[test]
+    fn rejects_invalid_faults() {
+        for invalid in [
+            "pause",
+            "stop:tikv@10s",
+            "pause:tidb@10s",
+            "pause:tikv.first@10s",
+            "pause:tikv",
+            "pause:tikv@soon",
+            "pause:tikv@10s+",
+            "kill:pd@-1s",
+            "pause:pd@1e30s",
+            "pause:pd@10s+1e30s",
+        ] {
+            assert!(invalid.parse::<Fault>().is_err(), "`{}`", invalid);
+        }
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("500ms"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_duration("10s"), Some(Duration::from_secs(10)));
+        assert_eq!(parse_duration("1.5"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("0"), Some(Duration::ZERO));
+        assert_eq!(parse_duration("-1s"), None);
+        assert_eq!(parse_duration("inf"), None);
+        assert_eq!(parse_duration("NaN"), None);
+        assert_eq!(parse_duration("1e30s"), None);
+        assert_eq!(parse_duration("1e30"), None);
+        assert_eq!(parse_duration("10m"), None);
+        assert_eq!(parse_duration(""), None);
+    }
+}
@@ -1,18 +1,37 @@
+use crate::error::Error;
 use crate::runnable::Runnable;
 
 pub(crate) mod case;
+pub(crate) mod env;
 pub(crate) mod generate;
+pub(crate) mod replay;
+pub(crate) mod report;
+pub(crate) mod seed;
+pub(crate) mod snapshot;
+pub(crate) mod suite;
 
 pub enum TestingCommand<'e> {
-    Case(case::TestCase<'e>),
-    Generate(generate::GenerateCommand),
+    Case(Box<case::TestCase<'e>>),
+    Env(env::EnvCommand),
+    Generate(Box<generate::GenerateCommand>),
+    Replay(Box<replay::ReplayCommand>),
+    Report(report::ReportCommand),
+    Seed(Box<seed::SeedCommand>),
+    Snapshot(Box<snapshot::SnapshotCommand>),
+    Suite(Box<suite::SuiteCommand>),
 }
 
 impl Runnable for TestingCommand<'_> {
-    async fn run(&mut self) -> Result<(), reqwest::Error> {
+    async fn run(&mut self) -> Result<(), Error> {
         match self {
             TestingCommand::Case(test_case) => test_case.run().await?,
+            TestingCommand::Env(env_command) => env_command.run().await?,
             TestingCommand::Generate(generate_command) => generate_command.run().await?,
+            TestingCommand::Replay(replay_command) => replay_command.run().await?,
+            TestingCommand::Report(report_command) => report_command.run().await?,
+            TestingCommand::Seed(seed_command) => seed_command.run().await?,
+            TestingCommand::Snapshot(snapshot_command) => snapshot_command.run().await?,
+            TestingCommand::Suite(suite_command) => suite_command.run().await?,
         }
         Ok(())
     }
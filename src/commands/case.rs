@@ -1,241 +1,1124 @@
 use clap_complete::Shell;
 use futures::stream::FuturesUnordered;
 use futures::StreamExt;
-use rand::distributions::Alphanumeric;
-use rand::Rng;
 use serde::{Deserialize, Serialize};
 use surrealdb::RecordId;
 
+use crate::assertion::Assertion;
+use crate::chaos::{Chaos, Fault};
+use crate::commands::seed::SeedFile;
+use crate::database::{self, DbOptions};
+use crate::environment::EnvState;
+use crate::error::Error;
+use crate::generator::{self, Access, ContentOptions, Distribution, Generator};
+use crate::junit::{self, JunitCase, TestSuite};
+use crate::jwt;
+use crate::metrics;
+use crate::monitor::Monitor;
+use crate::playground::{self, Playground, PlaygroundOptions, Topology};
+use crate::results::{
+    as_ms, CreatedWishes, Durability, ErrorKind, Failure, Operation, Overall, Recorder, RunResults,
+    Sample,
+};
+use crate::run_dir::{tail, RunDir};
 use crate::runnable::Runnable;
-use std::io::{BufRead, BufReader};
-use std::path::Path;
-use std::process::{Command, Stdio};
-use std::sync::{Arc, Mutex};
+use crate::server::{self, SERVER_LOG};
+use crate::traffic::{Exchange, TrafficLog};
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime};
 
 pub struct TestCase<'e> {
     case: CaseNum,
     shell: &'e Shell,
-    executable: &'e Path,
+    /// Not needed when attaching to an environment.
+    executable: Option<&'e Path>,
+    options: CaseOptions,
+    playground: Option<Playground>,
+    /// The environment of `testing env up` the case is attached to.
+    env: Option<EnvState>,
+    /// What survived the restart of case five.
+    durability: Option<Durability>,
+    /// The wishes the case created.
+    created_wishes: Option<CreatedWishes>,
+    /// The results of the finished run, for `testing suite`.
+    outcome: Option<CaseOutcome>,
+    /// Random data of the run.
+    generator: Generator,
+    /// Start of the names of all users the run registers, to find its data again.
+    user_prefix: String,
     surrealdb_handle: Arc<Mutex<Option<std::process::Child>>>,
 }
 
+/// Options shared by all test cases.
+#[derive(Clone, Debug)]
+pub struct CaseOptions {
+    /// Write the results of the run as JSON to this file.
+    pub output: Option<PathBuf>,
+    /// Write every request of the run as one CSV row to this file.
+    pub raw_samples: Option<PathBuf>,
+    /// Write every request and response of the run as JSON lines to this file, for
+    /// `testing replay`.
+    pub record: Option<PathBuf>,
+    /// Thresholds the run has to satisfy to pass.
+    pub assertions: Vec<Assertion>,
+    /// Write the run and its assertions as JUnit XML to this file.
+    pub junit: Option<PathBuf>,
+    /// Abort the run once more than this fraction of requests failed.
+    pub max_error_rate: Option<f64>,
+    /// Give up on a request after this long and count it as timeout.
+    pub request_timeout: Duration,
+    /// Serve Prometheus metrics of the run on this address.
+    pub metrics_listen: Option<SocketAddr>,
+    /// Sample resources of the server and TiKV processes this often, `None` to not monitor them.
+    pub monitor_interval: Option<Duration>,
+    /// Every run gets its own directory for logs and results below this one.
+    pub runs_dir: PathBuf,
+    /// The cluster to start with tiup playground.
+    pub playground: PlaygroundOptions,
+    /// Faults to inject into the cluster during the measured phase.
+    pub faults: Vec<Fault>,
+    /// Attach to the environment of `testing env up` with this state file instead of
+    /// starting a playground and server.
+    pub env: Option<PathBuf>,
+    /// Throw away the data of the attached environment before the case.
+    pub wipe: bool,
+    /// Where the server keeps its data, to remove what the run created.
+    pub database: DbOptions,
+    /// Leave the users and wishes of the run in the database.
+    pub keep_data: bool,
+    /// Read the wishes of the users of `testing seed` instead of creating some.
+    pub seeded: Option<PathBuf>,
+    /// Restore the database from this snapshot before the case.
+    pub snapshot: Option<PathBuf>,
+    /// Seed of the random users and wishes, a random one if `None`.
+    pub seed: Option<u64>,
+    /// What the content of the created wishes looks like.
+    pub content: ContentOptions,
+    /// How the created wishes are shared by the users.
+    pub distribution: Distribution,
+    pub tokens: TokenOptions,
+}
+
+/// How the tokens of the logins are checked.
+#[derive(Clone, Debug, Default)]
+pub struct TokenOptions {
+    /// HMAC secret to verify their signatures with, they are not verified without.
+    pub key: Option<String>,
+    /// How long the run is planned to take, to warn about tokens that expire before.
+    pub run_time: Option<Duration>,
+}
+
+#[derive(Clone, Debug)]
 pub enum CaseNum {
     One(CaseOneArgs),
     Two(CaseTwoArgs),
     Three(CaseThreeArgs),
     Four(CaseFourArgs),
+    Five(CaseFiveArgs),
+    Six(CaseSixArgs),
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct CaseOneArgs {
     pub n: u32,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct CaseTwoArgs {
     pub wishes: u32,
+    /// Users the wishes are shared by.
+    pub users: u32,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct CaseThreeArgs {
     pub wishes: u32,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct CaseFourArgs {
     pub times: u32,
+    /// Users with one wish each that the reads are spread over.
+    pub users: u32,
+    pub access: Access,
+}
+
+#[derive(Clone, Debug)]
+pub struct CaseFiveArgs {
+    pub wishes: u32,
+    /// Users the wishes are shared by.
+    pub users: u32,
+    /// Restart the tiup cluster too, its data is kept under the tag.
+    pub restart_cluster: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct CaseSixArgs {
+    /// Reads of all users together.
+    pub times: u32,
+    pub users: u32,
+    /// Wishes of each user.
+    pub wishes: u32,
+}
+
+/// What a finished run left behind.
+pub struct CaseOutcome {
+    pub results: RunResults,
+    pub junit: TestSuite,
+    pub run_dir: PathBuf,
+}
+
+/// What the setup of a case created for its measured phase.
+#[derive(Default)]
+struct Prepared {
+    sessions: Vec<Arc<Session>>,
+    /// The wishes of each user as the server returned them.
+    wishes: Vec<Vec<Wish>>,
+}
+
+impl CaseNum {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CaseNum::One(_) => "one",
+            CaseNum::Two(_) => "two",
+            CaseNum::Three(_) => "three",
+            CaseNum::Four(_) => "four",
+            CaseNum::Five(_) => "five",
+            CaseNum::Six(_) => "six",
+        }
+    }
+
+    /// The `-n` of the case.
+    pub fn size(&self) -> u32 {
+        match self {
+            CaseNum::One(args) => args.n,
+            CaseNum::Two(args) => args.wishes,
+            CaseNum::Three(args) => args.wishes,
+            CaseNum::Four(args) => args.times,
+            CaseNum::Five(args) => args.wishes,
+            CaseNum::Six(args) => args.times,
+        }
+    }
+
+    /// The same case with another `-n`.
+    pub fn with_size(&self, n: u32) -> Self {
+        let mut case = self.clone();
+        match &mut case {
+            CaseNum::One(args) => args.n = n,
+            CaseNum::Two(args) => args.wishes = n,
+            CaseNum::Three(args) => args.wishes = n,
+            CaseNum::Four(args) => args.times = n,
+            CaseNum::Five(args) => args.wishes = n,
+            CaseNum::Six(args) => args.times = n,
+        }
+        case
+    }
+
+    /// The same case with `n` users, for the cases whose number of users can be changed.
+    pub fn with_users(&self, n: u32) -> Self {
+        let mut case = self.clone();
+        match &mut case {
+            CaseNum::One(_) | CaseNum::Three(_) => {}
+            CaseNum::Two(args) => args.users = n,
+            CaseNum::Four(args) => args.users = n,
+            CaseNum::Five(args) => args.users = n,
+            CaseNum::Six(args) => args.users = n,
+        }
+        case
+    }
+
+    pub fn description(&self) -> String {
+        match self {
+            CaseNum::One(args) => format!("Register {} users.", args.n),
+            CaseNum::Two(args) => format!(
+                "Register {} users and create {} wishes between them.",
+                args.users, args.wishes
+            ),
+            CaseNum::Three(args) => format!("Select {} wishes in one request.", args.wishes),
+            CaseNum::Four(args) if args.users > 1 => format!(
+                "Select the wish of one of {} users {} times with {} access.",
+                args.users, args.times, args.access
+            ),
+            CaseNum::Four(args) => format!("Select one wish {} times.", args.times),
+            CaseNum::Five(args) => format!(
+                "Create {} wishes of {} users, restart the server{} and check that they survived.",
+                args.wishes,
+                args.users,
+                if args.restart_cluster {
+                    " and the cluster"
+                } else {
+                    ""
+                }
+            ),
+            CaseNum::Six(args) => format!(
+                "{} users with {} wishes select their own wishes concurrently, {} times in total.",
+                args.users,
+                u64::from(args.users) * u64::from(args.wishes),
+                args.times
+            ),
+        }
+    }
+}
+
+/// `<case>[:<n>]`, e.g. `three:10000`, as the cases of `testing suite` are given.
+impl FromStr for CaseNum {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, n) = match s.split_once(':') {
+            Some((name, n)) => (
+                name,
+                n.parse::<u32>()
+                    .map_err(|_| format!("invalid size `{}` of case `{}`", n, s))?,
+            ),
+            None => (s, 1000),
+        };
+        Ok(match name {
+            "one" => CaseNum::One(CaseOneArgs { n }),
+            "two" => CaseNum::Two(CaseTwoArgs {
+                wishes: n,
+                users: 10,
+            }),
+            "three" => CaseNum::Three(CaseThreeArgs { wishes: n }),
+            "four" => CaseNum::Four(CaseFourArgs {
+                times: n,
+                users: 1,
+                access: Access::Uniform,
+            }),
+            "five" => CaseNum::Five(CaseFiveArgs {
+                wishes: n,
+                users: 10,
+                restart_cluster: false,
+            }),
+            "six" => CaseNum::Six(CaseSixArgs {
+                times: n,
+                users: 100,
+                wishes: 10,
+            }),
+            _ => {
+                return Err(format!(
+                "invalid case `{}`, use `<one|two|three|four|five|six>[:<n>]`, e.g. `three:10000`",
+                s
+            ))
+            }
+        })
+    }
 }
 
 const URL_PREFIX: &str = "http://localhost:8080/api";
+/// Names of the users a run registers start with this and the tag of the run.
+const RUN_USER_PREFIX: &str = "test";
+const MIB: f64 = 1024.0 * 1024.0;
+/// Lines of the server log shown when a case fails.
+const SERVER_LOG_TAIL: usize = 20;
 
 impl<'e> Runnable for TestCase<'e> {
-    async fn run(&mut self) -> Result<(), reqwest::Error> {
-        let client = reqwest::Client::new();
-        self.before();
-        let mut start_time = std::time::Instant::now();
-        match &self.case {
-            CaseNum::One(args) => {
-                println!("Test case one: Register {} users.", args.n);
-                register_users(&client, args.n as usize).await?;
+    async fn run(&mut self) -> Result<(), Error> {
+        let client = reqwest::Client::builder()
+            .timeout(self.options.request_timeout)
+            .build()?;
+        let traffic = match &self.options.record {
+            Some(record) => Some(TrafficLog::create(record)?),
+            None => None,
+        };
+        let recorder = Recorder::new()
+            .with_max_error_rate(self.options.max_error_rate)
+            .with_traffic(traffic);
+        let run_dir = RunDir::create(
+            &self.options.runs_dir,
+            &format!("case-{}", self.case.name()),
+        )?;
+        println!("Writing logs and results to {}.", run_dir.path().display());
+        self.before(&run_dir)?;
+        let metrics = match self.options.metrics_listen {
+            Some(address) => {
+                match metrics::serve(address, recorder.clone(), self.case.name()).await {
+                    Ok(metrics) => Some(metrics),
+                    Err(err) => {
+                        // a taken port must not leave the cluster running
+                        self.after();
+                        return Err(err);
+                    }
+                }
             }
-            CaseNum::Two(args) => {
-                println!(
-                    "Test case two: Register 10 users and create {} wishes each.",
-                    args.wishes / 10
-                );
-                let credentials = register_users(&client, 10).await?;
-                let jwts = login_users(&client, &credentials).await?;
-                create_wishes(&client, Arc::new(jwts), args.wishes).await?;
+            None => None,
+        };
+        let started_at = SystemTime::now();
+        println!(
+            "Test case {}: {}",
+            self.case.name(),
+            self.case.description()
+        );
+        println!(
+            "Generating data with seed {}, repeat it with `--seed {}`. Wishes are {}.",
+            self.generator.seed(),
+            self.generator.seed(),
+            self.generator.content()
+        );
+        let mut monitor = None;
+        let mut chaos = None;
+        let outcome = match self.prepare(&client, &recorder).await {
+            Ok(prepared) => {
+                recorder.start_measuring();
+                monitor = self.start_monitor(&recorder);
+                match self.start_chaos(&recorder, &run_dir) {
+                    Ok(started) => {
+                        chaos = started;
+                        self.execute(&client, &recorder, &prepared, &run_dir, monitor.as_mut())
+                            .await
+                    }
+                    Err(err) => Err(err),
+                }
             }
-            CaseNum::Three(args) => {
+            Err(err) => Err(err),
+        };
+        recorder.stop_measuring();
+        let elapsed = recorder.elapsed();
+        let abort_reason = recorder.abort_reason();
+        match &abort_reason {
+            Some(reason) => eprintln!("Test case {}!", reason),
+            None => println!(
+                "Test case completed in {},{} seconds.",
+                elapsed.as_secs(),
+                elapsed.as_millis() % 1000
+            ),
+        }
+        let resources = monitor.map(Monitor::stop).unwrap_or_default();
+        let (faults, restarted) = chaos.map(|chaos| chaos.stop(&recorder)).unwrap_or_default();
+        let adopted_by_env = self.env.is_some() && !restarted.is_empty();
+        for (replaced, component) in restarted {
+            if let Some(playground) = &mut self.playground {
+                playground.adopt(replaced, component);
+            } else if let Some(env) = &mut self.env {
+                env.adopt(replaced, component);
+            }
+        }
+        if let (true, Some(env), Some(state_file)) = (adopted_by_env, &self.env, &self.options.env)
+        {
+            if let Err(err) = env.save(state_file) {
+                eprintln!("Failed to update the environment state: {}", err);
+            }
+        }
+        if !self.options.keep_data {
+            self.remove_data().await;
+        }
+        let topology = self.topology().cloned();
+        self.after();
+        if let Some(metrics) = metrics {
+            metrics.abort();
+            // wait for the listener to be closed
+            let _ = metrics.await;
+        }
+        if let Err(err) = outcome {
+            print_server_log(&self.server_log(&run_dir));
+            return Err(err);
+        }
+
+        let mut results = RunResults::new(
+            self.case.name(),
+            &self.case.description(),
+            started_at,
+            &recorder,
+        );
+        results.resources = resources;
+        results.topology = topology;
+        results.faults = faults;
+        results.durability = self.durability.take();
+        results.created_wishes = self.created_wishes.take();
+        results.seed = Some(self.generator.seed());
+        results.wish_content = Some(self.generator.content().to_string());
+        print_errors(&results);
+        print_resources(&results);
+        print_faults(&results);
+        results.save(&run_dir.file("results.json"))?;
+        if let Some(output) = &self.options.output {
+            results.save(output)?;
+            println!("Results written to {}.", output.display());
+        }
+        if let Some(raw_samples) = &self.options.raw_samples {
+            recorder.write_csv(raw_samples)?;
+            println!("Raw samples written to {}.", raw_samples.display());
+        }
+        if let (Some(traffic), Some(record)) = (recorder.traffic(), &self.options.record) {
+            traffic.flush()?;
+            println!(
+                "Traffic recorded to {}, replay it with `testing replay {}`.",
+                record.display(),
+                record.display()
+            );
+        }
+
+        let suite = self.check(&results, abort_reason);
+        if let Some(junit) = &self.options.junit {
+            junit::write(junit, std::slice::from_ref(&suite))?;
+            println!("JUnit report written to {}.", junit.display());
+        }
+        let (failures, checks) = (suite.failures(), suite.cases.len());
+        self.outcome = Some(CaseOutcome {
+            results,
+            junit: suite,
+            run_dir: run_dir.path().to_path_buf(),
+        });
+        match failures {
+            0 => Ok(()),
+            failures => {
+                print_server_log(&self.server_log(&run_dir));
+                Err(Error::Failed(format!(
+                    "{} of {} checks of test case {} failed",
+                    failures,
+                    checks,
+                    self.case.name()
+                )))
+            }
+        }
+    }
+}
+
+/// Show the end of the server log, as it usually tells why requests failed.
+fn print_server_log(path: &Path) {
+    let lines = tail(path, SERVER_LOG_TAIL);
+    if lines.is_empty() {
+        return;
+    }
+    eprintln!("Last lines of {}:", path.display());
+    for line in lines {
+        eprintln!("  {}", line);
+    }
+}
+
+fn print_errors(results: &RunResults) {
+    let overall = &results.overall;
+    if overall.errors == 0 {
+        return;
+    }
+    let kinds: Vec<String> = overall
+        .errors_by_kind
+        .iter()
+        .map(|(kind, count)| format!("{}: {}", kind, count))
+        .collect();
+    eprintln!(
+        "{} of {} measured requests failed ({}).",
+        overall.errors,
+        overall.count,
+        kinds.join(", ")
+    );
+}
+
+fn print_faults(results: &RunResults) {
+    for fault in &results.faults {
+        let Some(injected) = fault.injected_ms else {
+            println!(
+                "Fault {} was not injected, the run ended before.",
+                fault.fault
+            );
+            continue;
+        };
+        match fault.recovered_ms {
+            Some(recovered) => println!(
+                "Fault {} on {}: injected after {:.1} s, recovered after {:.1} s.",
+                fault.fault,
+                fault.target,
+                injected / 1000.0,
+                recovered / 1000.0
+            ),
+            None => println!(
+                "Fault {} on {}: injected after {:.1} s, lasted until the end.",
+                fault.fault,
+                fault.target,
+                injected / 1000.0
+            ),
+        }
+        for (window, overall) in [
+            ("before", &fault.before),
+            ("during", &fault.during),
+            ("after", &fault.after),
+        ] {
+            if let Some(overall) = overall {
                 println!(
-                    "Test case three: Select {} wishes in one request.",
-                    args.wishes
+                    "  {:<6} {:>7} requests, {:>6.2}% errors, p50 {:.2} ms, p99 {:.2} ms, max {:.2} ms",
+                    window,
+                    overall.count,
+                    error_rate(overall) * 100.0,
+                    overall.latency.p50_ms,
+                    overall.latency.p99_ms,
+                    overall.latency.max_ms
                 );
-                println!("Registering and creating wishes...");
-                let credentials = register_users(&client, 1).await?;
-                let jwts = login_users(&client, &credentials).await?;
-                let arc_jwts = Arc::new(jwts);
-                create_wishes(&client, Arc::clone(&arc_jwts), args.wishes).await?;
-                println!("Start selecting...");
-                // restart timer because registration does not count in this test case
-                start_time = std::time::Instant::now();
-                get_wishes(&client, arc_jwts.get(0).unwrap(), 1).await?;
-            }
-            CaseNum::Four(args) => {
-                println!("Test case four: Select one wish {} times.", args.times);
-                println!("Registering user and creating wish...");
-                let credentials = register_users(&client, 1).await?;
-                let jwts = login_users(&client, &credentials).await?;
-                let arc_jwts = Arc::new(jwts);
-                create_wishes(&client, Arc::clone(&arc_jwts), 1).await?;
-                println!("Start selecting...");
-                // restart timer because registration does not count in this test case
-                start_time = std::time::Instant::now();
-                get_wishes(&client, arc_jwts.get(0).unwrap(), args.times).await?;
             }
         }
-        let elapsed = start_time.elapsed();
+    }
+}
+
+fn error_rate(overall: &Overall) -> f64 {
+    if overall.count == 0 {
+        0.0
+    } else {
+        overall.errors as f64 / overall.count as f64
+    }
+}
+
+fn print_resources(results: &RunResults) {
+    for process in &results.resources {
+        let peak = &process.peak;
         println!(
-            "Test case completed in {},{} seconds.",
-            elapsed.as_secs(),
-            elapsed.as_millis() % 1000
+            "Peak resources of {} ({}): {:.0}% cpu, {:.1} MiB rss, {} threads, {} open files, {:.1} MiB read, {:.1} MiB written.",
+            process.name,
+            process.pid,
+            peak.cpu_percent,
+            peak.rss_bytes as f64 / MIB,
+            peak.threads,
+            peak.open_fds,
+            peak.read_bytes as f64 / MIB,
+            peak.write_bytes as f64 / MIB
         );
-        self.after();
-        Ok(())
     }
 }
 
+/// Send a request, record its sample and return the body of a successful response.
+pub async fn send(
+    recorder: &Recorder,
+    operation: Operation,
+    request: reqwest::RequestBuilder,
+) -> Result<String, Failure> {
+    send_checked(recorder, operation, request, false).await
+}
+
+/// Send the request of `request` with the token of `session`. When the server rejects the
+/// token, the user logs in again and the request is sent once more. Only the second attempt
+/// counts then, so an expired token does not show up as error.
+async fn send_authorized(
+    client: &reqwest::Client,
+    recorder: &Recorder,
+    operation: Operation,
+    session: &Session,
+    request: impl Fn(&str) -> reqwest::RequestBuilder,
+    expect_json: bool,
+) -> Result<String, Failure> {
+    let jwt = session.jwt();
+    match send_unrecorded(recorder, operation, request(&jwt), expect_json).await {
+        (
+            Err(Failure {
+                kind: ErrorKind::Status(401),
+                ..
+            }),
+            _,
+        ) => {
+            session.log_in_again(client, recorder, &jwt).await?;
+            send_checked(recorder, operation, request(&session.jwt()), expect_json).await
+        }
+        (result, sample) => {
+            if let Some(sample) = sample {
+                recorder.record(sample);
+            }
+            result
+        }
+    }
+}
+
+fn header_map(headers: &reqwest::header::HeaderMap) -> BTreeMap<String, String> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            (
+                name.to_string(),
+                String::from_utf8_lossy(value.as_bytes()).into_owned(),
+            )
+        })
+        .collect()
+}
+
+async fn send_checked(
+    recorder: &Recorder,
+    operation: Operation,
+    request: reqwest::RequestBuilder,
+    expect_json: bool,
+) -> Result<String, Failure> {
+    let (result, sample) = send_unrecorded(recorder, operation, request, expect_json).await;
+    if let Some(sample) = sample {
+        recorder.record(sample);
+    }
+    result
+}
+
+/// Send a request and return its sample without recording it. There is no sample if the
+/// request could not even be built.
+async fn send_unrecorded(
+    recorder: &Recorder,
+    operation: Operation,
+    request: reqwest::RequestBuilder,
+    expect_json: bool,
+) -> (Result<String, Failure>, Option<Sample>) {
+    let (client, request) = request.build_split();
+    let request = match request {
+        Ok(request) => request,
+        Err(err) => return (Err(Failure::from(err)), None),
+    };
+    // taken before sending, as sending consumes the request
+    let mut exchange = recorder.traffic().map(|_| Exchange {
+        operation,
+        start_ms: 0.0,
+        latency_ms: 0.0,
+        method: request.method().to_string(),
+        path: match request.url().query() {
+            Some(query) => format!("{}?{}", request.url().path(), query),
+            None => request.url().path().to_owned(),
+        },
+        headers: header_map(request.headers()),
+        body: request
+            .body()
+            .and_then(reqwest::Body::as_bytes)
+            .map(|body| String::from_utf8_lossy(body).into_owned()),
+        status: None,
+        response_headers: BTreeMap::new(),
+        response_body: None,
+    });
+    let start = Instant::now();
+    let mut status = None;
+    let mut bytes = 0;
+    let result = async {
+        let response = client.execute(request).await.map_err(Failure::from)?;
+        status = Some(response.status().as_u16());
+        if let Some(exchange) = &mut exchange {
+            exchange.response_headers = header_map(response.headers());
+        }
+        let body = response.text().await.map_err(Failure::from)?;
+        bytes = body.len() as u64;
+        if let Some(exchange) = &mut exchange {
+            exchange.response_body = Some(body.clone());
+        }
+        match status {
+            Some(code) if !(200..300).contains(&code) => Err(Failure {
+                kind: ErrorKind::Status(code),
+                message: body,
+            }),
+            _ if expect_json => match serde_json::from_str::<serde_json::Value>(&body) {
+                Ok(_) => Ok(body),
+                Err(err) => Err(Failure {
+                    kind: ErrorKind::Decode,
+                    message: err.to_string(),
+                }),
+            },
+            _ => Ok(body),
+        }
+    }
+    .await;
+    let latency = start.elapsed();
+    if let (Some(traffic), Some(mut exchange)) = (recorder.traffic(), exchange) {
+        exchange.start_ms = as_ms(recorder.offset(start));
+        exchange.latency_ms = as_ms(latency);
+        exchange.status = status;
+        traffic.write(&exchange);
+    }
+    let (error, message) = match &result {
+        Ok(_) => (None, None),
+        Err(failure) => (Some(failure.kind), Some(failure.message.clone())),
+    };
+    let sample = Sample {
+        operation,
+        start: recorder.offset(start),
+        latency,
+        status,
+        bytes,
+        error,
+        message,
+    };
+    (result, Some(sample))
+}
+
 async fn get_wishes(
     client: &reqwest::Client,
-    jwt: &String,
+    recorder: &Recorder,
+    session: &Session,
     times: u32,
-) -> Result<(), reqwest::Error> {
+) -> Result<(), Error> {
     for _ in 0..times {
+        if recorder.aborted() {
+            break;
+        }
         let wish_url = format!("{}/wishes?with_username=false", URL_PREFIX);
-        let res = client.get(wish_url).bearer_auth(jwt).send().await;
-        match res {
-            Ok(response) => {
-                if response.status() != 200 {
-                    eprintln!("Failed to get wishes! {}", response.text().await.unwrap());
+        let res = send_authorized(
+            client,
+            recorder,
+            Operation::GetWishes,
+            session,
+            |jwt| client.get(&wish_url).bearer_auth(jwt),
+            true,
+        )
+        .await;
+        if let Err(failure) = res {
+            eprintln!("Failed to get wishes: {}", failure);
+        }
+    }
+    Ok(())
+}
+
+/// Every user of `sessions` gets their own wishes at the same time as the others, `times`
+/// requests in total.
+async fn get_wishes_concurrently(
+    client: &reqwest::Client,
+    recorder: &Recorder,
+    sessions: &[Arc<Session>],
+    times: u32,
+) -> Result<(), Error> {
+    let mut tasks = FuturesUnordered::new();
+    let users = sessions.len() as u32;
+    for (i, session) in sessions.iter().enumerate() {
+        let client = client.clone();
+        let recorder = recorder.clone();
+        let session = Arc::clone(session);
+        // the first users read once more when the reads cannot be shared evenly
+        let times = times / users + u32::from((i as u32) < times % users);
+        tasks.push(tokio::spawn(async move {
+            get_wishes(&client, &recorder, &session, times).await
+        }));
+    }
+
+    while let Some(result) = tasks.next().await {
+        match result {
+            Ok(result) => result?,
+            Err(err) => eprintln!("Task panicked: {}", err),
+        }
+    }
+    Ok(())
+}
+
+/// The wishes the server returns for the user of `session`.
+async fn fetch_wishes(
+    client: &reqwest::Client,
+    recorder: &Recorder,
+    session: &Session,
+) -> Result<Vec<Wish>, Error> {
+    let wish_url = format!("{}/wishes?with_username=false", URL_PREFIX);
+    let body = send_authorized(
+        client,
+        recorder,
+        Operation::GetWishes,
+        session,
+        |jwt| client.get(&wish_url).bearer_auth(jwt),
+        true,
+    )
+    .await
+    .map_err(|failure| Error::Failed(format!("failed to get wishes: {}", failure)))?;
+    Ok(serde_json::from_str(&body)?)
+}
+
+/// Log every user in again and compare their wishes with the ones from before the restart.
+async fn check_durability(
+    client: &reqwest::Client,
+    recorder: &Recorder,
+    prepared: &Prepared,
+    tokens: &TokenOptions,
+) -> Result<Durability, Error> {
+    let mut durability = Durability::default();
+    for (prepared_session, before) in prepared.sessions.iter().zip(&prepared.wishes) {
+        let credentials = &prepared_session.credentials;
+        durability.users += 1;
+        durability.wishes += before.len() as u64;
+        let session =
+            match login_users(client, recorder, std::slice::from_ref(credentials), tokens).await {
+                Ok(mut sessions) => sessions.remove(0),
+                Err(err) => {
+                    eprintln!("{}", err);
+                    durability.users_lost += 1;
+                    durability.wishes_missing += before.len() as u64;
+                    continue;
                 }
-            }
+            };
+        let after: HashMap<String, Wish> = match fetch_wishes(client, recorder, &session).await {
+            Ok(wishes) => wishes
+                .into_iter()
+                .map(|wish| (wish.id.to_string(), wish))
+                .collect(),
             Err(err) => {
-                eprintln!("Failed to get wishes: {}", err);
+                eprintln!("Cannot check the wishes of {}: {}", credentials.name, err);
+                durability.wishes_missing += before.len() as u64;
+                continue;
+            }
+        };
+        for wish in before {
+            match after.get(&wish.id.to_string()) {
+                None => {
+                    eprintln!("Wish {} of {} is missing.", wish.id, credentials.name);
+                    durability.wishes_missing += 1;
+                }
+                Some(found) if found.content != wish.content || found.status != wish.status => {
+                    eprintln!(
+                        "Wish {} of {} changed from {:?} ({:?}) to {:?} ({:?}).",
+                        wish.id,
+                        credentials.name,
+                        wish.content,
+                        wish.status,
+                        found.content,
+                        found.status
+                    );
+                    durability.wishes_changed += 1;
+                }
+                Some(_) => {}
             }
         }
     }
-    Ok(())
+    Ok(durability)
 }
 
-/// For each user represented by a jwt, create n / len(jwts) wishes
+/// Create `n_wishes` wishes shared by the users of `sessions` as `distribution` says.
+/// Returns how many the server created.
 async fn create_wishes(
     client: &reqwest::Client,
-    jwts: Arc<Vec<String>>,
-    n_wishes: u32,
-) -> Result<(), reqwest::Error> {
+    recorder: &Recorder,
+    generator: &Generator,
+    sessions: &[Arc<Session>],
+    n_wishes: u64,
+    distribution: Distribution,
+) -> Result<u64, Error> {
     let mut tasks = FuturesUnordered::new();
+    let shares = generator.shares(distribution, n_wishes, sessions.len());
 
-    for i in 0..jwts.len() {
-        let jwts = Arc::clone(&jwts);
+    for (session, share) in sessions.iter().zip(shares) {
+        let session = Arc::clone(session);
         let client = client.clone(); // Clone the client for each task
+        let recorder = recorder.clone();
+        // generated up front, so the seed decides which user gets which wish
+        let contents: Vec<String> = (0..share).map(|_| generator.wish()).collect();
         tasks.push(tokio::spawn(async move {
-            for _ in 0..n_wishes / jwts.len() as u32 {
-                let wish = WishCreateRequest {
-                    content: generate_wish(),
-                };
+            let mut created = 0;
+            for content in contents {
+                if recorder.aborted() {
+                    break;
+                }
+                let wish = WishCreateRequest { content };
                 let wish_url = format!("{}/wish", URL_PREFIX);
-                let res = client
-                    .post(wish_url)
-                    .bearer_auth(jwts[i].clone())
-                    .json(&wish)
-                    .send()
-                    .await;
-
-                match &res {
-                    Ok(response) => {
-                        if response.status() != 200 {
-                            eprintln!("Failed to create wish: {}", wish.content);
-                        }
-                    }
-                    Err(err) => {
-                        eprintln!("Request failed for wish {}: {}", wish.content, err);
+                let res = send_authorized(
+                    &client,
+                    &recorder,
+                    Operation::CreateWish,
+                    &session,
+                    |jwt| client.post(&wish_url).bearer_auth(jwt).json(&wish),
+                    false,
+                )
+                .await;
+
+                match res {
+                    Ok(_) => created += 1,
+                    Err(failure) => {
+                        eprintln!("Failed to create wish {}: {}", wish.content, failure)
                     }
                 }
             }
+            created
         }));
     }
 
     // Wait for all tasks to finish
+    let mut created = 0;
     while let Some(result) = tasks.next().await {
-        if let Err(err) = result {
-            eprintln!("Task panicked: {}", err);
+        match result {
+            Ok(count) => created += count,
+            Err(err) => eprintln!("Task panicked: {}", err),
         }
     }
 
-    Ok(())
+    Ok(created)
 }
 
-async fn login_users(
+/// Print how many of the requested wishes were created.
+fn created_wishes(requested: u64, created: u64, distribution: Distribution) -> CreatedWishes {
+    println!(
+        "Created {} of {} wishes, shared {} by the users.",
+        created, requested, distribution
+    );
+    CreatedWishes {
+        requested,
+        created,
+        distribution: distribution.to_string(),
+    }
+}
+
+/// A logged in user, who logs in again when the server rejects the token.
+pub struct Session {
+    credentials: Credentials,
+    jwt: RwLock<String>,
+}
+
+impl Session {
+    fn jwt(&self) -> String {
+        self.jwt.read().expect("failed to lock the token").clone()
+    }
+
+    /// Log in again, unless another request already did since `rejected` was rejected.
+    async fn log_in_again(
+        &self,
+        client: &reqwest::Client,
+        recorder: &Recorder,
+        rejected: &str,
+    ) -> Result<(), Failure> {
+        if self.jwt() != rejected {
+            return Ok(());
+        }
+        println!(
+            "Logging in user {} again, the server rejected the token.",
+            self.credentials.name
+        );
+        let jwt = log_in(client, recorder, &self.credentials).await?;
+        *self.jwt.write().expect("failed to lock the token") = jwt;
+        Ok(())
+    }
+}
+
+/// The token of a new login of the user of `credentials`.
+async fn log_in(
     client: &reqwest::Client,
-    credentials: &Vec<Credentials>,
-) -> Result<Vec<String>, reqwest::Error> {
-    let mut jwts = Vec::new();
-    for cred in credentials {
-        let login_url = format!("{}/login", URL_PREFIX);
-        let response = client
+    recorder: &Recorder,
+    credentials: &Credentials,
+) -> Result<String, Failure> {
+    let login_url = format!("{}/login", URL_PREFIX);
+    let body = send(
+        recorder,
+        Operation::Login,
+        client
             .post(login_url)
             .header("Content-Type", "application/json")
-            .json(cred)
-            .send()
-            .await?;
-        let jwt = response.text().await?;
-        if jwt.starts_with("\"") {
-            jwts.push(jwt[1..jwt.len() - 1].to_string());
-        } else {
-            jwts.push(jwt);
+            .json(credentials),
+    )
+    .await?;
+    jwt::from_login_response(&body).map_err(|message| Failure {
+        kind: ErrorKind::Decode,
+        message,
+    })
+}
+
+/// Log the users in and check their tokens, warning about the ones that expire before the
+/// planned end of the run. Users who fail to log in get no session, the run only fails if
+/// none of them can.
+async fn login_users(
+    client: &reqwest::Client,
+    recorder: &Recorder,
+    credentials: &[Credentials],
+    tokens: &TokenOptions,
+) -> Result<Vec<Arc<Session>>, Error> {
+    let planned_end = tokens
+        .run_time
+        .and_then(|run_time| SystemTime::now().checked_add(run_time));
+    let mut sessions = Vec::new();
+    let mut expiring: Vec<SystemTime> = Vec::new();
+    let mut undecodable: Vec<String> = Vec::new();
+    let mut failed = 0;
+    for cred in credentials {
+        let jwt = match log_in(client, recorder, cred).await {
+            Ok(jwt) => jwt,
+            Err(failure) => {
+                eprintln!("Failed to log in user {}: {}", cred.name, failure);
+                failed += 1;
+                continue;
+            }
+        };
+        match jwt::decode(&jwt, tokens.key.as_deref()) {
+            Ok(claims) => {
+                if let (Some(planned_end), Some(expires_at)) = (planned_end, claims.expires_at()) {
+                    if expires_at < planned_end {
+                        expiring.push(expires_at);
+                    }
+                }
+            }
+            Err(err) if tokens.key.is_some() => {
+                return Err(Error::Failed(format!(
+                    "the token of user {} is not valid: {}",
+                    cred.name, err
+                )))
+            }
+            Err(err) => undecodable.push(err),
         }
+        sessions.push(Arc::new(Session {
+            credentials: cred.clone(),
+            jwt: RwLock::new(jwt),
+        }));
+    }
+    if sessions.is_empty() && !credentials.is_empty() {
+        return Err(Error::Failed(format!(
+            "none of the {} users could log in",
+            credentials.len()
+        )));
     }
-    Ok(jwts)
+    if failed > 0 {
+        eprintln!(
+            "Warning: {} of {} users could not log in, the run goes on without them.",
+            failed,
+            credentials.len()
+        );
+    }
+    if let Some(err) = undecodable.first() {
+        eprintln!(
+            "Warning: {} of {} tokens cannot be decoded, so their expiry is not checked: {}",
+            undecodable.len(),
+            sessions.len(),
+            err
+        );
+    }
+    if let (Some(first), Some(planned_end)) = (expiring.iter().min(), planned_end) {
+        let seconds = |time: &SystemTime| {
+            time.duration_since(SystemTime::now())
+                .unwrap_or_default()
+                .as_secs()
+        };
+        eprintln!(
+            "Warning: {} of {} tokens expire before the planned end of the run in {} s, the first in {} s. Their users log in again when the server rejects them.",
+            expiring.len(),
+            sessions.len(),
+            seconds(&planned_end),
+            seconds(first)
+        );
+    }
+    Ok(sessions)
 }
 
+/// `test` and a random tag. Not drawn from the generator, so no `--seed` gives a run the
+/// prefix of other runs or of the seeded users.
+pub fn run_user_prefix() -> String {
+    format!("{}{}", RUN_USER_PREFIX, generator::random_tag(6))
+}
+
+/// Register `n` new users. Returns the credentials of the ones the server accepted.
 async fn register_users(
     client: &reqwest::Client,
+    recorder: &Recorder,
+    generator: &Generator,
+    prefix: &str,
     n: usize,
-) -> Result<Vec<Credentials>, reqwest::Error> {
+) -> Result<Vec<Credentials>, Error> {
     let all_credentials = Arc::new(Mutex::new(Vec::new()));
     let mut tasks = FuturesUnordered::new();
 
     for _ in 0..n {
         let all_credentials = Arc::clone(&all_credentials);
         let client = client.clone(); // Clone the client for each task
+        let recorder = recorder.clone();
+        let credentials = Credentials {
+            name: generator.username(prefix),
+            pass: generator.password(),
+        };
         tasks.push(tokio::spawn(async move {
-            let credentials = Credentials {
-                name: generate_username(),
-                pass: generate_password(),
-            };
-            all_credentials.lock().unwrap().push(credentials.clone());
+            if recorder.aborted() {
+                return;
+            }
             let register_url = format!("{}/register", URL_PREFIX);
-            let res = client
-                .post(register_url)
-                .header("Content-Type", "application/json")
-                .json(&credentials)
-                .send()
-                .await;
+            let res = send(
+                &recorder,
+                Operation::Register,
+                client
+                    .post(register_url)
+                    .header("Content-Type", "application/json")
+                    .json(&credentials),
+            )
+            .await;
 
-            match &res {
-                Ok(response) => {
-                    if response.status() != 200 {
-                        eprintln!("Failed to register user: {}", credentials.name);
-                    }
-                }
-                Err(err) => {
-                    eprintln!(
-                        "Request failed for register user {}: {}",
-                        credentials.name, err
-                    );
+            match res {
+                Ok(_) => all_credentials.lock().unwrap().push(credentials),
+                Err(failure) => {
+                    eprintln!("Failed to register user {}: {}", credentials.name, failure)
                 }
             }
         }));
@@ -248,12 +1131,23 @@ async fn register_users(
         }
     }
 
-    let all_credentials = all_credentials.lock().unwrap();
-    Ok(all_credentials.clone())
+    let all_credentials = all_credentials.lock().unwrap().clone();
+    if all_credentials.is_empty() && n > 0 {
+        return Err(Error::Failed(format!(
+            "none of the {} users could be registered",
+            n
+        )));
+    }
+    Ok(all_credentials)
 }
 
 impl<'e> TestCase<'e> {
-    pub fn new(case: CaseNum, executable: &'e Path, shell: &'e Shell) -> Self {
+    pub fn new(
+        case: CaseNum,
+        executable: Option<&'e Path>,
+        shell: &'e Shell,
+        options: CaseOptions,
+    ) -> Self {
         match std::env::consts::OS {
             "linux" => {}
             "macos" => {}
@@ -264,8 +1158,7 @@ impl<'e> TestCase<'e> {
         match Command::new(shell.to_string())
             .arg("-c")
             .arg("exec tiup --version")
-            .stdout(Stdio::piped())
-            .spawn()
+            .output()
         {
             Ok(_) => {}
             Err(e) => match e.kind() {
@@ -278,60 +1171,506 @@ impl<'e> TestCase<'e> {
             },
         }
 
+        let mut options = options;
+        // the run lasts at least until its last fault is over
+        let faults_end = options.faults.iter().map(Fault::end).max();
+        options.tokens.run_time = options.tokens.run_time.max(faults_end);
+        let generator = Generator::new(options.seed, options.content.clone());
         Self {
             case,
             shell,
             executable,
+            options,
+            playground: None,
+            env: None,
+            durability: None,
+            created_wishes: None,
+            outcome: None,
+            user_prefix: run_user_prefix(),
+            generator,
             surrealdb_handle: Arc::new(Mutex::new(None)),
         }
     }
 
-    fn before(&mut self) {
-        let mut tiup = Command::new(self.shell.to_string())
-            .arg("-c")
-            .arg("exec tiup playground --tag surrealdb --mode tikv-slim --pd 1 --kv 1")
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-            .expect("failed to start tiup playground");
-        let stdout = tiup.stdout.take().expect("failed to capture stdout");
-        // Wrap stdout in a buffered reader for line-by-line reading
-        let reader = BufReader::new(stdout);
-        let mut port = "".to_owned();
-        for line in reader.lines() {
-            let line = line.expect("failed to read line");
-            println!("{}", line);
-            if line.contains("PD Endpoints") {
-                port += line.split_whitespace().last().unwrap();
-            };
-            if line.contains("Grafana:") {
-                break; // tiup startup complete
+    /// The results of the run, once it finished.
+    pub fn take_outcome(&mut self) -> Option<CaseOutcome> {
+        self.outcome.take()
+    }
+
+    /// Remove the users the run registered and their wishes, so later runs against the
+    /// same cluster start from the same data.
+    async fn remove_data(&self) {
+        let Some(topology) = self.topology() else {
+            return;
+        };
+        // never remove the seeded users, even if their names had the prefix of the run
+        let keep: Vec<String> = match &self.options.seeded {
+            Some(seeded) => match SeedFile::load(seeded) {
+                Ok(seed) => seed.users.into_iter().map(|user| user.name).collect(),
+                Err(err) => {
+                    eprintln!("Not removing the data of the run: {}", err);
+                    return;
+                }
+            },
+            None => Vec::new(),
+        };
+        let removed = match database::connect(topology.pd_endpoint(), &self.options.database).await
+        {
+            Ok(db) => database::remove_users(&db, &self.user_prefix, &keep).await,
+            Err(err) => Err(err),
+        };
+        match removed {
+            Ok(removed) => println!(
+                "Removed {} users and {} wishes of the run.",
+                removed.users, removed.wishes
+            ),
+            Err(err) => eprintln!("Failed to remove the data of the run: {}", err),
+        }
+    }
+
+    fn start_monitor(&self, recorder: &Recorder) -> Option<Monitor> {
+        let interval = self.options.monitor_interval?;
+        Monitor::start(self.monitored_processes(), interval, recorder.clone())
+    }
+
+    /// Name and pid of the server and the PD and TiKV processes.
+    fn monitored_processes(&self) -> Vec<(String, u32)> {
+        let mut processes: Vec<(String, u32)> = Vec::new();
+        if let Some(pid) = self.server_pid() {
+            processes.push(("server".to_owned(), pid));
+        }
+        if let Some(topology) = self.topology() {
+            processes.extend(topology.storage_processes());
+        }
+        processes
+    }
+
+    fn start_chaos(&self, recorder: &Recorder, run_dir: &RunDir) -> Result<Option<Chaos>, Error> {
+        match self.topology() {
+            Some(topology) if !self.options.faults.is_empty() => {
+                Chaos::start(&self.options.faults, topology, recorder.clone(), run_dir).map(Some)
             }
+            _ => Ok(None),
         }
-        thread::sleep(Duration::from_secs(1));
-        *self
-            .surrealdb_handle
+    }
+
+    /// Create what the case needs before its measured phase.
+    async fn prepare(
+        &mut self,
+        client: &reqwest::Client,
+        recorder: &Recorder,
+    ) -> Result<Prepared, Error> {
+        if let Some(snapshot) = &self.options.snapshot {
+            self.restore_snapshot(snapshot).await?;
+        }
+        if let Some(seeded) = &self.options.seeded {
+            return self.log_in_seeded(client, recorder, seeded).await;
+        }
+        match &self.case {
+            CaseNum::One(_) | CaseNum::Two(_) => Ok(Prepared::default()),
+            CaseNum::Three(args) => {
+                println!("Registering and creating wishes...");
+                let credentials =
+                    register_users(client, recorder, &self.generator, &self.user_prefix, 1).await?;
+                let sessions =
+                    login_users(client, recorder, &credentials, &self.options.tokens).await?;
+                let created = create_wishes(
+                    client,
+                    recorder,
+                    &self.generator,
+                    &sessions,
+                    args.wishes.into(),
+                    self.options.distribution,
+                )
+                .await?;
+                self.created_wishes = Some(created_wishes(
+                    args.wishes.into(),
+                    created,
+                    self.options.distribution,
+                ));
+                Ok(Prepared {
+                    sessions,
+                    ..Default::default()
+                })
+            }
+            CaseNum::Four(args) => {
+                println!("Registering users and creating wishes...");
+                let credentials = register_users(
+                    client,
+                    recorder,
+                    &self.generator,
+                    &self.user_prefix,
+                    args.users as usize,
+                )
+                .await?;
+                let sessions =
+                    login_users(client, recorder, &credentials, &self.options.tokens).await?;
+                let created = create_wishes(
+                    client,
+                    recorder,
+                    &self.generator,
+                    &sessions,
+                    args.users.into(),
+                    Distribution::Equal,
+                )
+                .await?;
+                self.created_wishes = Some(created_wishes(
+                    args.users.into(),
+                    created,
+                    Distribution::Equal,
+                ));
+                Ok(Prepared {
+                    sessions,
+                    ..Default::default()
+                })
+            }
+            CaseNum::Five(args) => {
+                println!("Registering users and creating wishes...");
+                let credentials = register_users(
+                    client,
+                    recorder,
+                    &self.generator,
+                    &self.user_prefix,
+                    args.users as usize,
+                )
+                .await?;
+                let sessions =
+                    login_users(client, recorder, &credentials, &self.options.tokens).await?;
+                let created = create_wishes(
+                    client,
+                    recorder,
+                    &self.generator,
+                    &sessions,
+                    args.wishes.into(),
+                    self.options.distribution,
+                )
+                .await?;
+                self.created_wishes = Some(created_wishes(
+                    args.wishes.into(),
+                    created,
+                    self.options.distribution,
+                ));
+                let mut wishes = Vec::new();
+                for session in &sessions {
+                    wishes.push(fetch_wishes(client, recorder, session).await?);
+                }
+                let created: usize = wishes.iter().map(Vec::len).sum();
+                if created < args.wishes as usize {
+                    eprintln!(
+                        "Only {} of {} wishes were created, checking those.",
+                        created, args.wishes
+                    );
+                }
+                Ok(Prepared { sessions, wishes })
+            }
+            CaseNum::Six(args) => {
+                println!("Registering users and creating wishes...");
+                let credentials = register_users(
+                    client,
+                    recorder,
+                    &self.generator,
+                    &self.user_prefix,
+                    args.users as usize,
+                )
+                .await?;
+                let sessions =
+                    login_users(client, recorder, &credentials, &self.options.tokens).await?;
+                // in u64, as the product of two u32 arguments can overflow u32
+                let n_wishes = u64::from(args.users) * u64::from(args.wishes);
+                let created = create_wishes(
+                    client,
+                    recorder,
+                    &self.generator,
+                    &sessions,
+                    n_wishes,
+                    self.options.distribution,
+                )
+                .await?;
+                self.created_wishes =
+                    Some(created_wishes(n_wishes, created, self.options.distribution));
+                Ok(Prepared {
+                    sessions,
+                    ..Default::default()
+                })
+            }
+        }
+    }
+
+    /// Start from the data of a snapshot, so every run reads the same records.
+    async fn restore_snapshot(&self, snapshot: &Path) -> Result<(), Error> {
+        let topology = self
+            .topology()
+            .ok_or_else(|| Error::Failed("the playground is not running".to_owned()))?;
+        println!("Restoring snapshot {}...", snapshot.display());
+        let db = database::connect(topology.pd_endpoint(), &self.options.database).await?;
+        database::restore(&db, &self.options.database, snapshot).await
+    }
+
+    /// Log in the seeded user with the most wishes instead of preparing the case.
+    async fn log_in_seeded(
+        &self,
+        client: &reqwest::Client,
+        recorder: &Recorder,
+        seeded: &Path,
+    ) -> Result<Prepared, Error> {
+        if !matches!(
+            self.case,
+            CaseNum::Three(_) | CaseNum::Four(_) | CaseNum::Six(_)
+        ) {
+            return Err(Error::Invalid(format!(
+                "case {} cannot use seeded users, only the read cases three, four and six can",
+                self.case.name()
+            )));
+        }
+        let seed = SeedFile::load(seeded)?;
+        // the users with the most wishes, as many as the reads are spread over
+        let mut users: Vec<(Credentials, u64)> = seed.users.into_iter().zip(seed.wishes).collect();
+        users.sort_by_key(|(_, wishes)| std::cmp::Reverse(*wishes));
+        users.truncate(match &self.case {
+            CaseNum::Four(args) => args.users as usize,
+            CaseNum::Six(args) => args.users as usize,
+            _ => 1,
+        });
+        if users.is_empty() {
+            return Err(Error::Invalid(format!("{} has no users", seeded.display())));
+        }
+        for (credentials, wishes) in &users {
+            println!(
+                "Logging in seeded user {} with {} wishes...",
+                credentials.name, wishes
+            );
+        }
+        let credentials: Vec<Credentials> = users
+            .into_iter()
+            .map(|(credentials, _)| credentials)
+            .collect();
+        let sessions = login_users(client, recorder, &credentials, &self.options.tokens).await?;
+        Ok(Prepared {
+            sessions,
+            ..Default::default()
+        })
+    }
+
+    /// Send the measured requests of the case.
+    async fn execute(
+        &mut self,
+        client: &reqwest::Client,
+        recorder: &Recorder,
+        prepared: &Prepared,
+        run_dir: &RunDir,
+        monitor: Option<&mut Monitor>,
+    ) -> Result<(), Error> {
+        match &self.case {
+            CaseNum::One(args) => {
+                register_users(
+                    client,
+                    recorder,
+                    &self.generator,
+                    &self.user_prefix,
+                    args.n as usize,
+                )
+                .await?;
+            }
+            CaseNum::Two(args) => {
+                let credentials = register_users(
+                    client,
+                    recorder,
+                    &self.generator,
+                    &self.user_prefix,
+                    args.users as usize,
+                )
+                .await?;
+                let sessions =
+                    login_users(client, recorder, &credentials, &self.options.tokens).await?;
+                let created = create_wishes(
+                    client,
+                    recorder,
+                    &self.generator,
+                    &sessions,
+                    args.wishes.into(),
+                    self.options.distribution,
+                )
+                .await?;
+                self.created_wishes = Some(created_wishes(
+                    args.wishes.into(),
+                    created,
+                    self.options.distribution,
+                ));
+            }
+            CaseNum::Three(_) => {
+                println!("Start selecting...");
+                get_wishes(client, recorder, prepared.sessions.first().unwrap(), 1).await?;
+            }
+            CaseNum::Four(args) if prepared.sessions.len() > 1 => {
+                let accesses = self.generator.accesses(
+                    args.access,
+                    prepared.sessions.len(),
+                    args.times as usize,
+                );
+                let mut reads = vec![0usize; prepared.sessions.len()];
+                accesses.iter().for_each(|&user| reads[user] += 1);
+                println!(
+                    "Start selecting with {} access, the most read user gets {:.1} % of the reads...",
+                    args.access,
+                    100.0 * *reads.iter().max().unwrap_or(&0) as f64 / accesses.len().max(1) as f64
+                );
+                for user in accesses {
+                    if recorder.aborted() {
+                        break;
+                    }
+                    get_wishes(client, recorder, &prepared.sessions[user], 1).await?;
+                }
+            }
+            CaseNum::Four(args) => {
+                println!("Start selecting...");
+                get_wishes(
+                    client,
+                    recorder,
+                    prepared.sessions.first().unwrap(),
+                    args.times,
+                )
+                .await?;
+            }
+            CaseNum::Five(args) => {
+                let restart_cluster = args.restart_cluster;
+                self.restart(restart_cluster, run_dir)?;
+                if let Some(monitor) = monitor {
+                    monitor.follow(self.monitored_processes());
+                }
+                println!("Checking users and wishes...");
+                let durability =
+                    check_durability(client, recorder, prepared, &self.options.tokens).await?;
+                println!(
+                    "{} of {} users and {} of {} wishes survived the restart, {} wishes changed.",
+                    durability.users - durability.users_lost,
+                    durability.users,
+                    durability.wishes - durability.wishes_missing,
+                    durability.wishes,
+                    durability.wishes_changed
+                );
+                self.durability = Some(durability);
+            }
+            CaseNum::Six(args) => {
+                println!(
+                    "Start selecting with {} users concurrently...",
+                    prepared.sessions.len()
+                );
+                get_wishes_concurrently(client, recorder, &prepared.sessions, args.times).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Turn the run and the outcome of its assertions into a JUnit suite, printing each failure.
+    fn check(&self, results: &RunResults, abort_reason: Option<String>) -> TestSuite {
+        let classname = format!("testing.case.{}", self.case.name());
+        let time_s = results.elapsed_ms / 1000.0;
+        let overall = &results.overall;
+        let failure = if abort_reason.is_some() {
+            abort_reason
+        } else if overall.count == 0 {
+            Some("no requests were measured".to_owned())
+        } else if overall.errors == overall.count {
+            Some(format!("all {} requests failed", overall.count))
+        } else {
+            None
+        };
+        let mut cases = vec![JunitCase {
+            name: format!("case {}: {}", self.case.name(), self.case.description()),
+            classname: classname.clone(),
+            time_s,
+            failure,
+        }];
+        if let Some(durability) = &results.durability {
+            cases.push(JunitCase {
+                name: "users and wishes survive the restart".to_owned(),
+                classname: classname.clone(),
+                time_s: 0.0,
+                failure: durability.failure(),
+            });
+        }
+        for assertion in &self.options.assertions {
+            let outcome = assertion.check(results);
+            let passed = outcome.passed();
+            println!(
+                "Assertion {}: {} (actual {}).",
+                assertion,
+                if passed { "passed" } else { "FAILED" },
+                outcome.actual_text()
+            );
+            cases.push(JunitCase {
+                name: assertion.to_string(),
+                classname: classname.clone(),
+                time_s: 0.0,
+                failure: (!passed)
+                    .then(|| format!("expected {}, actual {}", assertion, outcome.actual_text())),
+            });
+        }
+        if let Some(failure) = &cases[0].failure {
+            eprintln!("Test case {} failed: {}.", self.case.name(), failure);
+        }
+        TestSuite {
+            name: format!("case {}", self.case.name()),
+            time_s,
+            cases,
+        }
+    }
+
+    fn server_pid(&self) -> Option<u32> {
+        if let Some(env) = &self.env {
+            return Some(env.server_pid);
+        }
+        self.surrealdb_handle
             .lock()
-            .expect("Failed to lock surrealdb handler") = Some(
-            Command::new(self.executable)
-                .arg(port)
-                .spawn()
-                .expect("failed to start surrealdb executable"),
-        );
-        println!("Successfully started SurrealDB server!");
-        // await backend to be ready
-        thread::sleep(Duration::from_secs(2));
+            .expect("failed to lock surreal handler")
+            .as_ref()
+            .map(|server| server.id())
+    }
+
+    /// The cluster the case runs against, started by itself or by `testing env up`.
+    fn topology(&self) -> Option<&Topology> {
+        match (&self.playground, &self.env) {
+            (Some(playground), _) => Some(playground.topology()),
+            (None, Some(env)) => Some(&env.topology),
+            (None, None) => None,
+        }
+    }
+
+    fn server_log(&self, run_dir: &RunDir) -> PathBuf {
+        match &self.env {
+            Some(env) => env.run_dir.join(SERVER_LOG),
+            None => run_dir.file(SERVER_LOG),
+        }
+    }
+
+    fn before(&mut self, run_dir: &RunDir) -> Result<(), Error> {
+        if let Some(state_file) = &self.options.env {
+            let mut env = EnvState::load(state_file)?;
+            env.check()?;
+            if self.options.wipe {
+                env.wipe(self.shell)?;
+                env.save(state_file)?;
+            }
+            println!(
+                "Attached to the environment of {}, its logs are in {}.",
+                state_file.display(),
+                env.run_dir.display()
+            );
+            self.env = Some(env);
+            return Ok(());
+        }
+        let playground = Playground::start(self.shell, &self.options.playground, run_dir)?;
+        let pd_endpoint = playground.topology().pd_endpoint().to_owned();
+        self.playground = Some(playground);
+        thread::sleep(Duration::from_secs(1));
+        self.start_server(&pd_endpoint, run_dir)?;
 
         // Handle Ctrl+C and send SIGINT to tiup
         let surrealdb_handle = Arc::clone(&self.surrealdb_handle);
+        let tag = self.options.playground.tag.clone();
         ctrlc::set_handler(move || {
             println!("Stopping tiup cluster!");
-            let pid = get_surreal_tiup_playground_pid().expect("failed to get tiup pid");
-            Command::new("kill")
-                .arg("-2")
-                .arg(pid.to_string())
-                .spawn()
-                .expect("failed to send SIGINT to tiup");
+            playground::interrupt(&tag);
             println!("Stopping surrealdb server!");
             if let Some(ref mut handle) = *surrealdb_handle.lock().unwrap() {
                 eprintln!("Sending interrupt signal to tiup...");
@@ -342,84 +1681,79 @@ impl<'e> TestCase<'e> {
             std::process::exit(0);
         })
         .expect("Error setting Ctrl-C handler");
+        Ok(())
     }
 
-    fn after(&self) {
+    fn after(&mut self) {
+        if self.env.is_some() {
+            println!("Leaving the environment running.");
+            return;
+        }
+        self.stop_server();
+        if let Some(mut playground) = self.playground.take() {
+            playground.stop();
+        }
+    }
+
+    fn start_server(&self, pd_endpoint: &str, run_dir: &RunDir) -> Result<(), Error> {
+        let executable = self
+            .executable
+            .expect("the server executable is required without --env");
+        let server = server::start(executable, pd_endpoint, run_dir)?;
+        *self
+            .surrealdb_handle
+            .lock()
+            .expect("Failed to lock surrealdb handler") = Some(server);
+        Ok(())
+    }
+
+    fn stop_server(&self) {
         println!("Stopping SurrealDB server!");
-        self.surrealdb_handle
+        if let Some(mut server) = self
+            .surrealdb_handle
             .lock()
             .expect("failed to lock surreal handler")
-            .as_mut()
-            .unwrap()
-            .kill()
-            .expect("failed to send SIGINT to surreal");
-        println!("Stopping tiup cluster!");
-        let pid = get_surreal_tiup_playground_pid().expect("failed to get tiup pid");
-        Command::new("kill")
-            .arg("-2")
-            .arg(pid.to_string())
-            .spawn()
-            .expect("failed to send SIGINT to tiup");
-    }
-}
-
-fn generate_username() -> String {
-    let random_chars: String = rand::thread_rng()
-        .sample_iter(&Alphanumeric)
-        .take(20)
-        .map(char::from)
-        .collect();
-
-    format!("test{}", random_chars)
-}
-
-fn generate_password() -> String {
-    let random_chars: String = rand::thread_rng()
-        .sample_iter(&Alphanumeric)
-        .take(20)
-        .map(char::from)
-        .collect();
-
-    format!("pass{}", random_chars)
-}
-
-fn generate_wish() -> String {
-    let random_chars: String = rand::thread_rng()
-        .sample_iter(&Alphanumeric)
-        .take(20)
-        .map(char::from)
-        .collect();
-
-    format!("wish{}", random_chars)
-}
-
-fn get_surreal_tiup_playground_pid() -> Option<u32> {
-    // Run the `tiup status` command
-    let output = Command::new("tiup")
-        .arg("status")
-        .output()
-        .expect("failed to execute tiup status");
-
-    // Convert the output to a string
-    let output_str = String::from_utf8_lossy(&output.stdout);
+            .take()
+        {
+            server.kill().expect("failed to send SIGINT to surreal");
+            server.wait().expect("failed to wait for surreal to stop");
+        }
+    }
 
-    // Iterate through the lines to find the component
-    for line in output_str.lines().skip(1) {
-        // Skip the header line
-        let columns: Vec<&str> = line.split_whitespace().collect();
-        if columns.len() >= 3 && columns[0] == "surrealdb" {
-            // Parse the PID
-            if let Ok(pid) = columns[2].parse::<u32>() {
-                return Some(pid);
+    /// Stop the server and optionally the cluster, then start them again.
+    fn restart(&mut self, cluster: bool, run_dir: &RunDir) -> Result<(), Error> {
+        if let (Some(env), Some(state_file)) = (&mut self.env, &self.options.env) {
+            if cluster {
+                return Err(Error::Invalid(
+                    "restarting the cluster of an environment from `testing env up` is not supported"
+                        .to_owned(),
+                ));
+            }
+            env.restart_server()?;
+            return env.save(state_file);
+        }
+        self.stop_server();
+        if cluster {
+            if let Some(mut playground) = self.playground.take() {
+                playground.stop();
             }
+            self.playground = Some(Playground::start(
+                self.shell,
+                &self.options.playground,
+                run_dir,
+            )?);
+            thread::sleep(Duration::from_secs(1));
         }
+        let pd_endpoint = match &self.playground {
+            Some(playground) => playground.topology().pd_endpoint().to_owned(),
+            None => return Err(Error::Failed("the playground is not running".to_owned())),
+        };
+        self.start_server(&pd_endpoint, run_dir)
     }
-
-    // If the component is not found
-    None
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[allow(dead_code)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum WishStatus {
     Submitted,
     CreationInProgress,
@@ -432,6 +1766,7 @@ pub struct WishCreateRequest {
     content: String,
 }
 
+#[allow(dead_code)]
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct InfoResponse {
     info: String,
@@ -439,6 +1774,7 @@ pub struct InfoResponse {
     session: Option<String>,
 }
 
+#[allow(dead_code)]
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum UserRole {
     Default,
@@ -447,10 +1783,11 @@ pub enum UserRole {
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Credentials {
-    name: String,
-    pass: String,
+    pub name: String,
+    pub pass: String,
 }
 
+#[allow(dead_code)]
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct WishContent {
     content: String,
@@ -459,12 +1796,13 @@ pub struct WishContent {
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Wish {
-    id: RecordId,
-    content: String,
-    status: WishStatus,
-    created_by: Option<RecordId>,
+    pub id: RecordId,
+    pub content: String,
+    pub status: WishStatus,
+    pub created_by: Option<RecordId>,
 }
 
+#[allow(dead_code)]
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct WishWithUsername {
     id: RecordId,
@@ -476,8 +1814,8 @@ pub struct WishWithUsername {
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct User {
-    id: RecordId,
-    name: String,
-    pass: String,
-    roles: Vec<UserRole>,
+    pub id: RecordId,
+    pub name: String,
+    pub pass: String,
+    pub roles: Vec<UserRole>,
 }
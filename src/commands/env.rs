@@ -0,0 +1,125 @@
+use clap_complete::Shell;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::environment::{self, EnvState};
+use crate::error::Error;
+use crate::playground::PlaygroundOptions;
+use crate::run_dir::RunDir;
+use crate::runnable::Runnable;
+
+/// Manage a playground and server that several cases can run against.
+pub struct EnvCommand {
+    action: EnvAction,
+    /// Where the state of the running environment is stored.
+    state_file: PathBuf,
+    shell: Shell,
+}
+
+pub enum EnvAction {
+    Up {
+        executable: PathBuf,
+        playground: PlaygroundOptions,
+        runs_dir: PathBuf,
+    },
+    Down {
+        /// Also remove the data tiup keeps under the tag.
+        clean: bool,
+    },
+    Status,
+}
+
+impl EnvCommand {
+    pub fn new(action: EnvAction, state_file: PathBuf, shell: Shell) -> Self {
+        Self {
+            action,
+            state_file,
+            shell,
+        }
+    }
+}
+
+impl Runnable for EnvCommand {
+    async fn run(&mut self) -> Result<(), Error> {
+        match &self.action {
+            EnvAction::Up {
+                executable,
+                playground,
+                runs_dir,
+            } => {
+                if let Ok(state) = EnvState::load(&self.state_file) {
+                    if state.check().is_ok() {
+                        return Err(Error::Invalid(format!(
+                            "an environment is already running, see `testing env status` or stop it with `testing env down` ({})",
+                            self.state_file.display()
+                        )));
+                    }
+                }
+                let run_dir = RunDir::create(runs_dir, "env")?;
+                let state = EnvState::up(&self.shell, executable, playground, &run_dir)?;
+                state.save(&self.state_file)?;
+                println!(
+                    "Environment is up, its state is in {}. Run cases against it with `testing case --env ...`, stop it with `testing env down`.",
+                    self.state_file.display()
+                );
+            }
+            EnvAction::Down { clean } => {
+                let state = EnvState::load(&self.state_file)?;
+                state.down();
+                if *clean {
+                    environment::clean(&state.playground.tag);
+                }
+                std::fs::remove_file(&self.state_file)?;
+                println!("Environment is down.");
+            }
+            EnvAction::Status => {
+                let Ok(state) = EnvState::load(&self.state_file) else {
+                    println!(
+                        "No environment is running ({} does not exist).",
+                        self.state_file.display()
+                    );
+                    return Ok(());
+                };
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+                println!(
+                    "Environment started {} s ago, logs in {}.",
+                    now.saturating_sub(state.started_at),
+                    state.run_dir.display()
+                );
+                println!(
+                    "Server {} ({}): {}",
+                    state.executable.display(),
+                    state.server_pid,
+                    status(state.server_pid)
+                );
+                println!(
+                    "Playground {} ({}): {}",
+                    state.playground.tag,
+                    state.tiup_pid,
+                    status(state.tiup_pid)
+                );
+                println!("Cluster: {}", state.topology);
+                for component in &state.topology.components {
+                    println!(
+                        "  {} ({}): {}",
+                        component.role,
+                        component.pid,
+                        status(component.pid)
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn status(pid: u32) -> &'static str {
+    if environment::running(pid) {
+        "running"
+    } else {
+        "not running"
+    }
+}
@@ -1,5 +1,6 @@
 use clap_complete::{generate, Generator, Shell};
 
+use crate::error::Error;
 use crate::runnable::Runnable;
 
 pub struct GenerateCommand {
@@ -14,7 +15,7 @@ impl GenerateCommand {
 }
 
 impl Runnable for GenerateCommand {
-    async fn run(&mut self) -> Result<(), reqwest::Error> {
+    async fn run(&mut self) -> Result<(), Error> {
         eprintln!("Generating completion file for {}...", self.shell);
         print_completions(self.shell, &mut self.command);
         Ok(())
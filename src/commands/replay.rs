@@ -0,0 +1,357 @@
+use futures::stream::FuturesUnordered;
+use futures::StreamExt;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::watch;
+
+use crate::commands::case::send;
+use crate::error::Error;
+use crate::jwt;
+use crate::results::{ErrorKind, Operation, Overall, Recorder, RunResults, Sample};
+use crate::run_dir::RunDir;
+use crate::runnable::Runnable;
+use crate::traffic::{self, Exchange};
+
+/// Send the requests of a run recorded with `case --record` again, e.g. against another
+/// build of the server.
+pub struct ReplayCommand {
+    traffic: PathBuf,
+    /// Where the server listens, e.g. `http://localhost:8080`.
+    url: String,
+    /// 2 sends the requests twice as fast as they were recorded.
+    speed: f64,
+    request_timeout: Duration,
+    runs_dir: PathBuf,
+    /// Write the results of the replay as JSON to this file.
+    output: Option<PathBuf>,
+}
+
+/// The tokens of the recorded logins, each replaced by the token of its replayed login once
+/// that is done.
+type Tokens = HashMap<String, watch::Sender<Option<String>>>;
+
+impl ReplayCommand {
+    pub fn new(
+        traffic: PathBuf,
+        url: String,
+        speed: f64,
+        request_timeout: Duration,
+        runs_dir: PathBuf,
+        output: Option<PathBuf>,
+    ) -> Self {
+        Self {
+            traffic,
+            url,
+            speed,
+            request_timeout,
+            runs_dir,
+            output,
+        }
+    }
+}
+
+impl Runnable for ReplayCommand {
+    async fn run(&mut self) -> Result<(), Error> {
+        let exchanges = traffic::load(&self.traffic)?;
+        let origin = match exchanges.first() {
+            Some(first) => first.start_ms,
+            None => {
+                return Err(Error::Invalid(format!(
+                    "{} has no recorded requests",
+                    self.traffic.display()
+                )))
+            }
+        };
+        // when each request is sent, from the start of the replay
+        let schedule = exchanges
+            .iter()
+            .map(|exchange| {
+                traffic::as_duration((exchange.start_ms - origin) / self.speed).ok_or_else(|| {
+                    Error::Invalid(format!(
+                        "the request at {} ms cannot be replayed at {}x speed",
+                        exchange.start_ms, self.speed
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let recorded: Vec<Sample> = exchanges.iter().map(recorded_sample).collect();
+        let tokens: Arc<Tokens> = Arc::new(
+            exchanges
+                .iter()
+                .filter_map(recorded_token)
+                .map(|token| (token, watch::channel(None).0))
+                .collect(),
+        );
+        let client = reqwest::Client::builder()
+            .timeout(self.request_timeout)
+            .build()?;
+        let run_dir = RunDir::create(&self.runs_dir, "replay")?;
+        let description = format!(
+            "Replay {} requests of {} at {}x speed against {}.",
+            exchanges.len(),
+            self.traffic.display(),
+            self.speed,
+            self.url
+        );
+        println!("{}", description);
+
+        let recorder = Recorder::new();
+        recorder.start_measuring();
+        let started_at = SystemTime::now();
+        let start = tokio::time::Instant::now();
+        let mut tasks = FuturesUnordered::new();
+        for (exchange, at) in exchanges.into_iter().zip(schedule) {
+            tokio::time::sleep_until(start + at).await;
+            let client = client.clone();
+            let recorder = recorder.clone();
+            let tokens = Arc::clone(&tokens);
+            let url = self.url.clone();
+            tasks.push(tokio::spawn(async move {
+                replay(&client, &recorder, &tokens, &url, exchange).await
+            }));
+        }
+        while let Some(result) = tasks.next().await {
+            if let Err(err) = result {
+                eprintln!("Task panicked: {}", err);
+            }
+        }
+        recorder.stop_measuring();
+        println!(
+            "Replay completed in {:.3} seconds.",
+            recorder.elapsed().as_secs_f64()
+        );
+
+        let results = RunResults::new("replay", &description, started_at, &recorder);
+        print!(
+            "{}",
+            render_comparison(&recorded, &recorder.measured_samples())
+        );
+        results.save(&run_dir.file("results.json"))?;
+        println!(
+            "Results written to {}.",
+            run_dir.file("results.json").display()
+        );
+        if let Some(output) = &self.output {
+            results.save(output)?;
+            println!("Results written to {}.", output.display());
+        }
+        Ok(())
+    }
+}
+
+/// Send the request of `exchange` with the tokens of the replayed logins.
+async fn replay(
+    client: &reqwest::Client,
+    recorder: &Recorder,
+    tokens: &Tokens,
+    url: &str,
+    exchange: Exchange,
+) {
+    let method = match reqwest::Method::from_bytes(exchange.method.as_bytes()) {
+        Ok(method) => method,
+        Err(_) => {
+            eprintln!("Skipping request with invalid method {}.", exchange.method);
+            // the requests of its user must not wait for a login that is never sent
+            publish_token(tokens, &exchange, None);
+            return;
+        }
+    };
+    let mut request = client.request(
+        method,
+        format!("{}{}", url.trim_end_matches('/'), exchange.path),
+    );
+    for (name, value) in &exchange.headers {
+        let value = match value
+            .strip_prefix("Bearer ")
+            .and_then(|token| tokens.get(token))
+        {
+            // wait for the login, as the requests of a user may be sent before it responded
+            Some(token) => {
+                let mut receiver = token.subscribe();
+                let replayed = receiver.wait_for(Option::is_some).await;
+                format!(
+                    "Bearer {}",
+                    replayed
+                        .ok()
+                        .and_then(|token| token.clone())
+                        .unwrap_or_default()
+                )
+            }
+            None => value.clone(),
+        };
+        request = request.header(name, value);
+    }
+    if let Some(body) = &exchange.body {
+        request = request.body(body.clone());
+    }
+    let result = send(recorder, exchange.operation, request).await;
+    publish_token(tokens, &exchange, result.as_deref().ok());
+    if let Err(failure) = result {
+        eprintln!(
+            "Failed to replay {} {}: {}",
+            exchange.method, exchange.path, failure
+        );
+    }
+}
+
+/// Hand the token of a replayed login with `response` to the requests of its user. Without
+/// a token in the response they get the recorded one, so they fail instead of waiting forever.
+fn publish_token(tokens: &Tokens, exchange: &Exchange, response: Option<&str>) {
+    let Some(recorded) = recorded_token(exchange) else {
+        return;
+    };
+    let replayed = response
+        .and_then(|body| jwt::from_login_response(body).ok())
+        .unwrap_or_else(|| recorded.clone());
+    if let Some(token) = tokens.get(&recorded) {
+        token.send_replace(Some(replayed));
+    }
+}
+
+/// The token a recorded login got.
+fn recorded_token(exchange: &Exchange) -> Option<String> {
+    if exchange.operation != Operation::Login
+        || !exchange
+            .status
+            .is_some_and(|code| (200..300).contains(&code))
+    {
+        return None;
+    }
+    jwt::from_login_response(exchange.response_body.as_deref()?).ok()
+}
+
+fn recorded_sample(exchange: &Exchange) -> Sample {
+    Sample {
+        operation: exchange.operation,
+        // checked when the traffic was loaded
+        start: traffic::as_duration(exchange.start_ms).unwrap_or_default(),
+        latency: traffic::as_duration(exchange.latency_ms).unwrap_or_default(),
+        status: exchange.status,
+        bytes: exchange
+            .response_body
+            .as_ref()
+            .map_or(0, |body| body.len() as u64),
+        error: match exchange.status {
+            Some(code) if (200..300).contains(&code) => None,
+            Some(code) => Some(ErrorKind::Status(code)),
+            None => Some(ErrorKind::Other),
+        },
+        message: None,
+    }
+}
+
+/// Requests, errors and latency per operation, as recorded and as replayed.
+fn render_comparison(recorded: &[Sample], replayed: &[Sample]) -> String {
+    let mut out = String::new();
+    writeln!(
+        out,
+        "{:<12} {:>10} {:>9} {:>9} {:>9} {:>10} {:>9} {:>9} {:>9}",
+        "operation",
+        "recorded",
+        "errors",
+        "p50 ms",
+        "p99 ms",
+        "replayed",
+        "errors",
+        "p50 ms",
+        "p99 ms"
+    )
+    .unwrap();
+    let mut operations: BTreeMap<Operation, (Vec<&Sample>, Vec<&Sample>)> = BTreeMap::new();
+    for sample in recorded {
+        operations
+            .entry(sample.operation)
+            .or_default()
+            .0
+            .push(sample);
+    }
+    for sample in replayed {
+        operations
+            .entry(sample.operation)
+            .or_default()
+            .1
+            .push(sample);
+    }
+    for (operation, (recorded, replayed)) in operations {
+        let recorded = Overall::new(&recorded, span(&recorded));
+        let replayed = Overall::new(&replayed, span(&replayed));
+        writeln!(
+            out,
+            "{:<12} {:>10} {:>9} {:>9.2} {:>9.2} {:>10} {:>9} {:>9.2} {:>9.2}",
+            operation.to_string(),
+            recorded.count,
+            recorded.errors,
+            recorded.latency.p50_ms,
+            recorded.latency.p99_ms,
+            replayed.count,
+            replayed.errors,
+            replayed.latency.p50_ms,
+            replayed.latency.p99_ms
+        )
+        .unwrap();
+    }
+    out
+}
+
+/// From the start of the first request to the end of the last one.
+fn span(samples: &[&Sample]) -> Duration {
+    let first = samples.iter().map(|sample| sample.start).min();
+    let last = samples
+        .iter()
+        .map(|sample| sample.start + sample.latency)
+        .max();
+    match (first, last) {
+        (Some(first), Some(last)) => last.saturating_sub(first),
+        _ => Duration::ZERO,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn login(method: &str, status: u16, token: &str) -> Exchange {
+        Exchange {
+            operation: Operation::Login,
+            start_ms: 0.0,
+            latency_ms: 1.0,
+            method: method.to_owned(),
+            path: "/api/login".to_owned(),
+            headers: BTreeMap::new(),
+            body: None,
+            status: Some(status),
+            response_headers: BTreeMap::new(),
+            response_body: Some(format!("\"{}\"", token)),
+        }
+    }
+
+    fn tokens(recorded: &str) -> Tokens {
+        HashMap::from([(recorded.to_owned(), watch::channel(None).0)])
+    }
+
+    #[test]
+    fn publishes_the_replayed_token() {
+        let tokens = tokens("old");
+        publish_token(&tokens, &login("POST", 200, "old"), Some("\"new\""));
+        assert_eq!(*tokens["old"].borrow(), Some("new".to_owned()));
+    }
+
+    #[test]
+    fn publishes_the_recorded_token_without_a_replayed_one() {
+        let tokens = tokens("old");
+        publish_token(&tokens, &login("NOT A METHOD", 200, "old"), None);
+        assert_eq!(*tokens["old"].borrow(), Some("old".to_owned()));
+    }
+
+    #[test]
+    fn failed_recorded_logins_have_no_token() {
+        assert_eq!(recorded_token(&login("POST", 401, "old")), None);
+        assert_eq!(
+            recorded_token(&login("POST", 200, "old")),
+            Some("old".to_owned())
+        );
+    }
+}
@@ -0,0 +1,730 @@
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use crate::chaos::FaultSummary;
+use crate::error::Error;
+use crate::junit::escape;
+use crate::monitor::{ProcessResources, ResourceSample};
+use crate::results::{Durability, Operation, OperationSummary, Overall, RunResults};
+use crate::runnable::Runnable;
+
+const CHART_WIDTH: f64 = 640.0;
+const CHART_HEIGHT: f64 = 240.0;
+const MARGIN_LEFT: f64 = 56.0;
+const MARGIN_BOTTOM: f64 = 40.0;
+const MARGIN_TOP: f64 = 28.0;
+const MIB: f64 = 1024.0 * 1024.0;
+const COLORS: [&str; 6] = [
+    "#4c78a8", "#f58518", "#54a24b", "#b279a2", "#e45756", "#72b7b2",
+];
+
+pub struct ReportCommand {
+    results: Vec<PathBuf>,
+    html: Option<PathBuf>,
+}
+
+impl ReportCommand {
+    pub fn new(results: Vec<PathBuf>, html: Option<PathBuf>) -> Self {
+        Self { results, html }
+    }
+}
+
+/// A loaded results file with the label it is shown under.
+struct Run {
+    label: String,
+    results: RunResults,
+}
+
+impl Runnable for ReportCommand {
+    async fn run(&mut self) -> Result<(), Error> {
+        let mut runs = Vec::new();
+        for (path, label) in self.results.iter().zip(labels(&self.results)) {
+            let results = RunResults::load(path).map_err(|err| {
+                Error::Invalid(format!("failed to load {}: {}", path.display(), err))
+            })?;
+            runs.push(Run { label, results });
+        }
+
+        if let Some(html) = &self.html {
+            std::fs::write(html, render_html(&runs))?;
+            println!("Report written to {}.", html.display());
+        } else {
+            print!("{}", render_text(&runs));
+        }
+        Ok(())
+    }
+}
+
+/// A label for each results file, its name without extension. Where names repeat, e.g. the
+/// `results.json` of run directories, the directory is named instead, and where that does not
+/// help either, the position of the file.
+fn labels(paths: &[PathBuf]) -> Vec<String> {
+    let name = |path: Option<&Path>| {
+        path.and_then(Path::file_stem)
+            .map(|stem| stem.to_string_lossy().into_owned())
+    };
+    let stems: Vec<String> = paths
+        .iter()
+        .map(|path| name(Some(path)).unwrap_or_else(|| path.display().to_string()))
+        .collect();
+    let mut labels: Vec<String> = paths
+        .iter()
+        .zip(&stems)
+        .map(|(path, stem)| {
+            if stems.iter().filter(|other| *other == stem).count() == 1 {
+                return stem.clone();
+            }
+            name(path.parent())
+                .filter(|parent| !parent.is_empty())
+                .unwrap_or_else(|| stem.clone())
+        })
+        .collect();
+    let unique: Vec<bool> = labels
+        .iter()
+        .map(|label| labels.iter().filter(|other| *other == label).count() == 1)
+        .collect();
+    for (index, label) in labels.iter_mut().enumerate() {
+        if !unique[index] {
+            *label = format!("{} #{}", label, index + 1);
+        }
+    }
+    labels
+}
+
+fn render_text(runs: &[Run]) -> String {
+    let mut out = String::new();
+    writeln!(
+        out,
+        "{:<24} {:<12} {:>8} {:>7} {:>10} {:>9} {:>9} {:>9} {:>9}",
+        "run", "operation", "requests", "errors", "req/s", "p50 ms", "p90 ms", "p99 ms", "max ms"
+    )
+    .unwrap();
+    for run in runs {
+        for op in &run.results.operations {
+            writeln!(
+                out,
+                "{:<24} {:<12} {:>8} {:>7} {:>10.1} {:>9.2} {:>9.2} {:>9.2} {:>9.2}",
+                run.label,
+                op.operation.to_string(),
+                op.count,
+                op.errors,
+                op.throughput,
+                op.latency.p50_ms,
+                op.latency.p90_ms,
+                op.latency.p99_ms,
+                op.latency.max_ms
+            )
+            .unwrap();
+        }
+    }
+    for run in runs {
+        for op in run.results.operations.iter().filter(|op| op.errors > 0) {
+            writeln!(
+                out,
+                "{} {} errors: {}",
+                run.label,
+                op.operation,
+                error_kinds(op)
+            )
+            .unwrap();
+        }
+    }
+    for run in runs {
+        if let Some(topology) = &run.results.topology {
+            writeln!(out, "{} cluster: {}", run.label, topology).unwrap();
+        }
+        if let Some(content) = &run.results.wish_content {
+            writeln!(out, "{} wishes: {}", run.label, content).unwrap();
+        }
+        if let Some(created) = &run.results.created_wishes {
+            writeln!(
+                out,
+                "{} created wishes: {} of {}, shared {} by the users",
+                run.label, created.created, created.requested, created.distribution
+            )
+            .unwrap();
+        }
+        if let Some(durability) = &run.results.durability {
+            writeln!(
+                out,
+                "{} durability: {}",
+                run.label,
+                durability_text(durability)
+            )
+            .unwrap();
+        }
+        for fault in &run.results.faults {
+            let windows = [&fault.before, &fault.during, &fault.after];
+            let column = |value: &dyn Fn(&Overall) -> String| {
+                windows
+                    .iter()
+                    .map(|window| window.as_ref().map(value).unwrap_or("-".to_owned()))
+                    .collect::<Vec<_>>()
+                    .join("/")
+            };
+            writeln!(
+                out,
+                "{} fault {} on {}: before/during/after {} requests, {} errors, p99 {} ms",
+                run.label,
+                fault.fault,
+                fault.target,
+                column(&|o| o.count.to_string()),
+                column(&|o| o.errors.to_string()),
+                column(&|o| format!("{:.2}", o.latency.p99_ms))
+            )
+            .unwrap();
+        }
+    }
+    out
+}
+
+fn durability_text(durability: &Durability) -> String {
+    durability.failure().unwrap_or(format!(
+        "all {} users and {} wishes survived",
+        durability.users, durability.wishes
+    ))
+}
+
+/// Failed requests per kind, e.g. `timeout: 2, http_500: 1`.
+fn error_kinds(op: &OperationSummary) -> String {
+    op.errors_by_kind
+        .iter()
+        .map(|(kind, count)| format!("{}: {}", kind, count))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn render_html(runs: &[Run]) -> String {
+    let mut out = String::new();
+    out.push_str(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Wishes test report</title>\n",
+    );
+    out.push_str(
+        "<style>\n\
+         body { font-family: sans-serif; margin: 2em; color: #222; }\n\
+         table { border-collapse: collapse; margin: 1em 0; }\n\
+         th, td { border: 1px solid #ccc; padding: 4px 8px; text-align: right; }\n\
+         th:first-child, td:first-child { text-align: left; }\n\
+         .better { color: #2a7a2a; } .worse { color: #b22; }\n\
+         svg { display: block; margin: 1em 0; }\n\
+         svg text { font-size: 11px; }\n\
+         </style>\n</head>\n<body>\n",
+    );
+    out.push_str("<h1>Wishes test report</h1>\n");
+
+    if runs.len() > 1 {
+        render_comparison(&mut out, runs);
+    }
+
+    for (index, run) in runs.iter().enumerate() {
+        let results = &run.results;
+        writeln!(
+            out,
+            "<h2 id=\"run-{}\">{}</h2>\n<p>Test case {}: {}<br>Started at {} (unix), measured phase took {:.3} s, {} requests, {} errors.</p>",
+            index,
+            escape(&run.label),
+            escape(&results.case),
+            escape(&results.description),
+            results.started_at,
+            results.elapsed_ms / 1000.0,
+            results.total_requests(),
+            results.total_errors()
+        )
+        .unwrap();
+        if let Some(topology) = &results.topology {
+            writeln!(out, "<p>Cluster: {}</p>", escape(&topology.to_string())).unwrap();
+        }
+        render_percentile_table(&mut out, &results.operations);
+        for op in &results.operations {
+            writeln!(out, "<h3>{}</h3>", op.operation).unwrap();
+            out.push_str(&histogram_chart(op));
+            out.push_str(&throughput_chart(op));
+        }
+        if let Some(durability) = &results.durability {
+            writeln!(
+                out,
+                "<p>Durability: {}</p>",
+                escape(&durability_text(durability))
+            )
+            .unwrap();
+        }
+        if !results.faults.is_empty() {
+            render_faults(&mut out, &results.faults);
+        }
+        if !results.resources.is_empty() {
+            render_resources(&mut out, &results.resources);
+        }
+    }
+
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn render_percentile_table(out: &mut String, operations: &[OperationSummary]) {
+    out.push_str(
+        "<table>\n<tr><th>operation</th><th>requests</th><th>errors</th><th>req/s</th><th>min ms</th><th>mean ms</th><th>p50 ms</th><th>p90 ms</th><th>p95 ms</th><th>p99 ms</th><th>max ms</th><th>error kinds</th></tr>\n",
+    );
+    for op in operations {
+        let l = &op.latency;
+        writeln!(
+            out,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{:.1}</td><td>{:.2}</td><td>{:.2}</td><td>{:.2}</td><td>{:.2}</td><td>{:.2}</td><td>{:.2}</td><td>{:.2}</td><td>{}</td></tr>",
+            op.operation,
+            op.count,
+            op.errors,
+            op.throughput,
+            l.min_ms,
+            l.mean_ms,
+            l.p50_ms,
+            l.p90_ms,
+            l.p95_ms,
+            l.p99_ms,
+            l.max_ms,
+            escape(&error_kinds(op))
+        )
+        .unwrap();
+    }
+    out.push_str("</table>\n");
+}
+
+fn render_faults(out: &mut String, faults: &[FaultSummary]) {
+    out.push_str("<h3>Faults</h3>\n");
+    out.push_str(
+        "<table>\n<tr><th>fault</th><th>window</th><th>from s</th><th>requests</th><th>errors</th><th>req/s</th><th>p50 ms</th><th>p99 ms</th><th>max ms</th></tr>\n",
+    );
+    for fault in faults {
+        let (Some(injected), Some(during)) = (fault.injected_ms, &fault.during) else {
+            writeln!(
+                out,
+                "<tr><td>{}</td><td colspan=\"8\">not injected, the run ended before</td></tr>",
+                escape(&fault.fault)
+            )
+            .unwrap();
+            continue;
+        };
+        let length = fault.recovered_ms.unwrap_or(injected) - injected;
+        let windows = [
+            (
+                "before",
+                (injected - length).max(0.0),
+                fault.before.as_ref(),
+            ),
+            ("during", injected, Some(during)),
+            (
+                "after",
+                fault.recovered_ms.unwrap_or_default(),
+                fault.after.as_ref(),
+            ),
+        ];
+        for (window, from_ms, overall) in windows {
+            let Some(overall) = overall else {
+                continue;
+            };
+            writeln!(
+                out,
+                "<tr><td>{} on {}</td><td>{}</td><td>{:.1}</td><td>{}</td><td>{}</td><td>{:.1}</td><td>{:.2}</td><td>{:.2}</td><td>{:.2}</td></tr>",
+                escape(&fault.fault),
+                escape(&fault.target),
+                window,
+                from_ms / 1000.0,
+                overall.count,
+                overall.errors,
+                overall.throughput,
+                overall.latency.p50_ms,
+                overall.latency.p99_ms,
+                overall.latency.max_ms
+            )
+            .unwrap();
+        }
+    }
+    out.push_str("</table>\n");
+}
+
+fn render_resources(out: &mut String, resources: &[ProcessResources]) {
+    out.push_str("<h3>Resources</h3>\n");
+    out.push_str(
+        "<table>\n<tr><th>process</th><th>pid</th><th>peak cpu %</th><th>peak rss MiB</th><th>peak threads</th><th>peak open files</th><th>read MiB</th><th>written MiB</th></tr>\n",
+    );
+    for process in resources {
+        let peak = &process.peak;
+        writeln!(
+            out,
+            "<tr><td>{}</td><td>{}</td><td>{:.0}</td><td>{:.1}</td><td>{}</td><td>{}</td><td>{:.1}</td><td>{:.1}</td></tr>",
+            escape(&process.name),
+            process.pid,
+            peak.cpu_percent,
+            peak.rss_bytes as f64 / MIB,
+            peak.threads,
+            peak.open_fds,
+            peak.read_bytes as f64 / MIB,
+            peak.write_bytes as f64 / MIB
+        )
+        .unwrap();
+    }
+    out.push_str("</table>\n");
+
+    // all processes are sampled together, so the first one tells the interval
+    let samples = &resources[0].samples;
+    let step = match (samples.first(), samples.last()) {
+        (Some(first), Some(last)) if samples.len() > 1 => {
+            (last.offset_ms - first.offset_ms) / (samples.len() - 1) as f64 / 1000.0
+        }
+        _ => 1.0,
+    };
+    let names: Vec<String> = resources
+        .iter()
+        .map(|process| format!("{} {}", process.name, process.pid))
+        .collect();
+    let series = |value: fn(&ResourceSample) -> f64| -> Vec<(&str, Vec<f64>)> {
+        resources
+            .iter()
+            .zip(&names)
+            .map(|(process, name)| (name.as_str(), process.samples.iter().map(value).collect()))
+            .collect()
+    };
+    out.push_str(&line_chart(
+        "cpu usage over time (%)",
+        step,
+        &series(|sample| sample.cpu_percent),
+    ));
+    out.push_str(&line_chart(
+        "rss over time (MiB)",
+        step,
+        &series(|sample| sample.rss_bytes as f64 / MIB),
+    ));
+}
+
+/// Compares every run against the first one, per operation.
+fn render_comparison(out: &mut String, runs: &[Run]) {
+    out.push_str("<h2>Comparison</h2>\n");
+    writeln!(
+        out,
+        "<p>Differences are relative to the first run, <b>{}</b>.</p>",
+        escape(&runs[0].label)
+    )
+    .unwrap();
+
+    let mut operations: Vec<Operation> = runs
+        .iter()
+        .flat_map(|run| run.results.operations.iter().map(|op| op.operation))
+        .collect();
+    operations.sort();
+    operations.dedup();
+
+    for operation in operations {
+        let entries: Vec<(&Run, &OperationSummary)> = runs
+            .iter()
+            .filter_map(|run| {
+                run.results
+                    .operations
+                    .iter()
+                    .find(|op| op.operation == operation)
+                    .map(|op| (run, op))
+            })
+            .collect();
+        let (_, base) = entries[0];
+
+        writeln!(out, "<h3>{}</h3>", operation).unwrap();
+        out.push_str(
+            "<table>\n<tr><th>run</th><th>requests</th><th>errors</th><th>req/s</th><th>p50 ms</th><th>p99 ms</th></tr>\n",
+        );
+        for (run, op) in &entries {
+            writeln!(
+                out,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{:.1} {}</td><td>{:.2} {}</td><td>{:.2} {}</td></tr>",
+                escape(&run.label),
+                op.count,
+                op.errors,
+                op.throughput,
+                delta(base.throughput, op.throughput, true),
+                op.latency.p50_ms,
+                delta(base.latency.p50_ms, op.latency.p50_ms, false),
+                op.latency.p99_ms,
+                delta(base.latency.p99_ms, op.latency.p99_ms, false)
+            )
+            .unwrap();
+        }
+        out.push_str("</table>\n");
+
+        let labels: Vec<String> = entries.iter().map(|(run, _)| run.label.clone()).collect();
+        out.push_str(&bar_chart(
+            &format!("{}: latency per run (ms)", operation),
+            &labels,
+            &[
+                (
+                    "p50",
+                    entries.iter().map(|(_, op)| op.latency.p50_ms).collect(),
+                ),
+                (
+                    "p99",
+                    entries.iter().map(|(_, op)| op.latency.p99_ms).collect(),
+                ),
+            ],
+        ));
+        out.push_str(&bar_chart(
+            &format!("{}: throughput per run (req/s)", operation),
+            &labels,
+            &[(
+                "req/s",
+                entries.iter().map(|(_, op)| op.throughput).collect(),
+            )],
+        ));
+    }
+}
+
+/// Relative change of `value` against `base`, coloured by whether it is an improvement.
+fn delta(base: f64, value: f64, higher_is_better: bool) -> String {
+    if base == 0.0 || base == value {
+        return String::new();
+    }
+    let change = (value - base) / base * 100.0;
+    let better = (change > 0.0) == higher_is_better;
+    format!(
+        "<span class=\"{}\">({:+.1}%)</span>",
+        if better { "better" } else { "worse" },
+        change
+    )
+}
+
+fn histogram_chart(op: &OperationSummary) -> String {
+    let first = op.histogram.iter().position(|b| b.count > 0);
+    let last = op.histogram.iter().rposition(|b| b.count > 0);
+    let (Some(first), Some(last)) = (first, last) else {
+        return String::new();
+    };
+    let buckets = &op.histogram[first..=last];
+    let labels: Vec<String> = buckets
+        .iter()
+        .map(|bucket| match bucket.upper_ms {
+            Some(upper) => format!("≤{}", upper),
+            None => "more".to_owned(),
+        })
+        .collect();
+    bar_chart(
+        &format!("{}: latency histogram (ms)", op.operation),
+        &labels,
+        &[(
+            "requests",
+            buckets.iter().map(|bucket| bucket.count as f64).collect(),
+        )],
+    )
+}
+
+fn throughput_chart(op: &OperationSummary) -> String {
+    let timeline = &op.timeline;
+    let per_second = 1000.0 / timeline.interval_ms as f64;
+    line_chart(
+        &format!("{}: throughput over time (req/s)", op.operation),
+        timeline.interval_ms as f64 / 1000.0,
+        &[
+            (
+                "requests",
+                timeline
+                    .requests
+                    .iter()
+                    .map(|n| *n as f64 * per_second)
+                    .collect(),
+            ),
+            (
+                "errors",
+                timeline
+                    .errors
+                    .iter()
+                    .map(|n| *n as f64 * per_second)
+                    .collect(),
+            ),
+        ],
+    )
+}
+
+/// Grouped bar chart with one group per label and one bar per series.
+fn bar_chart(title: &str, labels: &[String], series: &[(&str, Vec<f64>)]) -> String {
+    let mut svg = svg_start(title);
+    let max = nice_max(series.iter().flat_map(|(_, values)| values.iter().copied()));
+    y_axis(&mut svg, max);
+
+    let plot_width = CHART_WIDTH - MARGIN_LEFT - 10.0;
+    let group_width = plot_width / labels.len().max(1) as f64;
+    let bar_width = group_width * 0.8 / series.len().max(1) as f64;
+    for (i, label) in labels.iter().enumerate() {
+        let group_x = MARGIN_LEFT + i as f64 * group_width + group_width * 0.1;
+        for (s, (name, values)) in series.iter().enumerate() {
+            let value = values.get(i).copied().unwrap_or_default();
+            let height = value / max * plot_height();
+            writeln!(
+                svg,
+                "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\"><title>{} {}: {:.2}</title></rect>",
+                group_x + s as f64 * bar_width,
+                MARGIN_TOP + plot_height() - height,
+                bar_width,
+                height,
+                COLORS[s % COLORS.len()],
+                escape(label),
+                name,
+                value
+            )
+            .unwrap();
+        }
+        writeln!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>",
+            group_x + group_width * 0.4,
+            MARGIN_TOP + plot_height() + 16.0,
+            escape(label)
+        )
+        .unwrap();
+    }
+    legend(&mut svg, series.iter().map(|(name, _)| *name));
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Line chart of evenly spaced points, `step` seconds apart.
+fn line_chart(title: &str, step: f64, series: &[(&str, Vec<f64>)]) -> String {
+    let mut svg = svg_start(title);
+    let max = nice_max(series.iter().flat_map(|(_, values)| values.iter().copied()));
+    y_axis(&mut svg, max);
+
+    let points = series.iter().map(|(_, v)| v.len()).max().unwrap_or(0);
+    let plot_width = CHART_WIDTH - MARGIN_LEFT - 10.0;
+    let x_step = plot_width / (points.max(2) - 1) as f64;
+    for (s, (_, values)) in series.iter().enumerate() {
+        let coordinates: Vec<String> = values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| {
+                format!(
+                    "{:.1},{:.1}",
+                    MARGIN_LEFT + i as f64 * x_step,
+                    MARGIN_TOP + plot_height() - value / max * plot_height()
+                )
+            })
+            .collect();
+        writeln!(
+            svg,
+            "<polyline fill=\"none\" stroke=\"{}\" stroke-width=\"1.5\" points=\"{}\"/>",
+            COLORS[s % COLORS.len()],
+            coordinates.join(" ")
+        )
+        .unwrap();
+    }
+    for tick in 0..=4 {
+        let i = (points.saturating_sub(1)) as f64 * tick as f64 / 4.0;
+        writeln!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{:.1}s</text>",
+            MARGIN_LEFT + i * x_step,
+            MARGIN_TOP + plot_height() + 16.0,
+            i * step
+        )
+        .unwrap();
+    }
+    legend(&mut svg, series.iter().map(|(name, _)| *name));
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn svg_start(title: &str) -> String {
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n<text x=\"{}\" y=\"16\" font-weight=\"bold\">{}</text>\n",
+        CHART_WIDTH,
+        CHART_HEIGHT,
+        CHART_WIDTH,
+        CHART_HEIGHT,
+        MARGIN_LEFT,
+        escape(title)
+    )
+}
+
+fn y_axis(svg: &mut String, max: f64) {
+    for tick in 0..=4 {
+        let value = max * tick as f64 / 4.0;
+        let y = MARGIN_TOP + plot_height() - value / max * plot_height();
+        writeln!(
+            svg,
+            "<line x1=\"{}\" y1=\"{:.1}\" x2=\"{}\" y2=\"{:.1}\" stroke=\"#ddd\"/>\n<text x=\"{}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>",
+            MARGIN_LEFT,
+            y,
+            CHART_WIDTH - 10.0,
+            y,
+            MARGIN_LEFT - 4.0,
+            y + 4.0,
+            format_tick(value)
+        )
+        .unwrap();
+    }
+}
+
+fn legend<'a>(svg: &mut String, names: impl Iterator<Item = &'a str>) {
+    for (s, name) in names.enumerate() {
+        let x = CHART_WIDTH - 10.0 - (s + 1) as f64 * 90.0;
+        writeln!(
+            svg,
+            "<rect x=\"{:.1}\" y=\"6\" width=\"10\" height=\"10\" fill=\"{}\"/><text x=\"{:.1}\" y=\"15\">{}</text>",
+            x,
+            COLORS[s % COLORS.len()],
+            x + 14.0,
+            escape(name)
+        )
+        .unwrap();
+    }
+}
+
+fn plot_height() -> f64 {
+    CHART_HEIGHT - MARGIN_TOP - MARGIN_BOTTOM
+}
+
+/// Rounds the maximum of `values` up to 1, 2 or 5 times a power of ten.
+fn nice_max(values: impl Iterator<Item = f64>) -> f64 {
+    let max = values.fold(0.0, f64::max);
+    if max <= 0.0 {
+        return 1.0;
+    }
+    let magnitude = 10f64.powf(max.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .iter()
+        .map(|factor| factor * magnitude)
+        .find(|candidate| *candidate >= max)
+        .unwrap_or(10.0 * magnitude)
+}
+
+fn format_tick(value: f64) -> String {
+    format!("{}", (value * 100.0).round() / 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(paths: &[&str]) -> Vec<PathBuf> {
+        paths.iter().map(PathBuf::from).collect()
+    }
+
+    #[test]
+    fn labels_runs_by_file_name() {
+        assert_eq!(
+            labels(&paths(&["before.json", "out/after.json"])),
+            ["before", "after"]
+        );
+    }
+
+    #[test]
+    fn labels_runs_with_the_same_file_name_by_directory() {
+        assert_eq!(
+            labels(&paths(&[
+                "runs/1-case-three/results.json",
+                "runs/2-case-three/results.json",
+                "baseline.json"
+            ])),
+            ["1-case-three", "2-case-three", "baseline"]
+        );
+    }
+
+    #[test]
+    fn labels_the_same_file_by_position() {
+        assert_eq!(
+            labels(&paths(&["results.json", "results.json", "a/results.json"])),
+            ["results #1", "results #2", "a"]
+        );
+    }
+}
@@ -0,0 +1,192 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use surrealdb::RecordId;
+
+use crate::commands::case::{Credentials, User, UserRole, Wish, WishStatus};
+use crate::database::{self, DbOptions};
+use crate::environment::EnvState;
+use crate::error::Error;
+use crate::generator::{ContentOptions, Distribution, Generator};
+use crate::runnable::Runnable;
+
+/// Insert users and wishes directly into the database of a running environment.
+pub struct SeedCommand {
+    options: SeedOptions,
+    state_file: PathBuf,
+    database: DbOptions,
+    /// Where the credentials of the seeded users are written.
+    output: PathBuf,
+    generator: Generator,
+}
+
+/// What to insert.
+#[derive(Debug)]
+pub struct SeedOptions {
+    pub users: u32,
+    /// Shared by the users as `distribution` says.
+    pub wishes: u64,
+    /// Records inserted per statement.
+    pub batch_size: usize,
+    /// Store the passwords without hashing them.
+    pub plain_passwords: bool,
+    pub content: ContentOptions,
+    pub distribution: Distribution,
+}
+
+/// Names of seeded users start with this, cases register `test` users, so cleaning up after
+/// a case never matches them.
+const SEED_USER_PREFIX: &str = "seed";
+
+/// `seed` and a tag drawn from `generator`, so the same seed gives the same users.
+fn seed_user_prefix(generator: &Generator) -> String {
+    format!("{}{}", SEED_USER_PREFIX, generator.chars(6))
+}
+
+/// The users of `testing seed`, so cases can log in as them.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SeedFile {
+    pub users: Vec<Credentials>,
+    /// How many wishes each user has, in the order of `users`.
+    pub wishes: Vec<u64>,
+}
+
+impl SeedFile {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let file = std::fs::File::open(path).map_err(|err| {
+            Error::Invalid(format!(
+                "cannot read the seeded users {}, create them with `testing seed`: {}",
+                path.display(),
+                err
+            ))
+        })?;
+        Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+    }
+
+    fn save(&self, path: &Path) -> Result<(), Error> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = std::fs::File::create(path)?;
+        serde_json::to_writer_pretty(std::io::BufWriter::new(file), self)?;
+        Ok(())
+    }
+}
+
+impl SeedCommand {
+    pub fn new(
+        options: SeedOptions,
+        state_file: PathBuf,
+        database: DbOptions,
+        output: PathBuf,
+        seed: Option<u64>,
+    ) -> Self {
+        Self {
+            generator: Generator::new(seed, options.content.clone()),
+            options,
+            state_file,
+            database,
+            output,
+        }
+    }
+}
+
+impl Runnable for SeedCommand {
+    async fn run(&mut self) -> Result<(), Error> {
+        let env = EnvState::load(&self.state_file)?;
+        env.check()?;
+        let db = database::connect(env.topology.pd_endpoint(), &self.database).await?;
+        let start = Instant::now();
+        let generator = &self.generator;
+        println!(
+            "Generating data with seed {}, repeat it with `--seed {}`. Wishes are {}.",
+            generator.seed(),
+            generator.seed(),
+            generator.content()
+        );
+
+        let prefix = seed_user_prefix(generator);
+        let credentials: Vec<Credentials> = (0..self.options.users)
+            .map(|_| Credentials {
+                name: generator.username(&prefix),
+                pass: generator.password(),
+            })
+            .collect();
+        let users: Vec<User> = credentials
+            .iter()
+            .map(|credentials| User {
+                id: RecordId::from_table_key("user", generator.chars(20).to_lowercase()),
+                name: credentials.name.clone(),
+                pass: credentials.pass.clone(),
+                roles: vec![UserRole::Default],
+            })
+            .collect();
+        println!("Inserting {} users...", users.len());
+        for batch in users.chunks(self.options.batch_size) {
+            database::insert_users(&db, batch.to_vec(), self.options.plain_passwords).await?;
+        }
+
+        let n_users = users.len() as u64;
+        let wishes = generator.shares(self.options.distribution, self.options.wishes, users.len());
+        println!(
+            "Inserting {} wishes, shared {} by the users...",
+            self.options.wishes, self.options.distribution
+        );
+        let mut inserted = 0;
+        let mut batch = Vec::with_capacity(self.options.batch_size);
+        for (user, count) in users.iter().zip(&wishes) {
+            for _ in 0..*count {
+                batch.push(Wish {
+                    id: RecordId::from_table_key("wish", generator.chars(20).to_lowercase()),
+                    content: generator.wish(),
+                    status: WishStatus::Submitted,
+                    created_by: Some(user.id.clone()),
+                });
+                if batch.len() == self.options.batch_size {
+                    inserted += batch.len();
+                    database::insert_wishes(&db, std::mem::take(&mut batch)).await?;
+                    println!("Inserted {} of {} wishes.", inserted, self.options.wishes);
+                }
+            }
+        }
+        if !batch.is_empty() {
+            inserted += batch.len();
+            database::insert_wishes(&db, batch).await?;
+            println!("Inserted {} of {} wishes.", inserted, self.options.wishes);
+        }
+
+        SeedFile {
+            users: credentials,
+            wishes,
+        }
+        .save(&self.output)?;
+        println!(
+            "Seeded {} users and {} wishes in {:.1} s. Their credentials are in {}, use them with `testing case --env --seeded {} three`.",
+            n_users,
+            inserted,
+            start.elapsed().as_secs_f64(),
+            self.output.display(),
+            self.output.display()
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::case::run_user_prefix;
+
+    #[test]
+    fn seeded_users_never_get_the_prefix_of_a_run() {
+        let seeded = seed_user_prefix(&Generator::new(Some(42), ContentOptions::default()));
+        let run = run_user_prefix();
+        assert!(seeded.starts_with(SEED_USER_PREFIX));
+        assert!(!seeded.starts_with(&run));
+        assert!(!seeded.starts_with("test"));
+        // the same seed gives the same seeded users, but not the same run
+        let again = seed_user_prefix(&Generator::new(Some(42), ContentOptions::default()));
+        assert_eq!(seeded, again);
+        assert_ne!(run, run_user_prefix());
+    }
+}
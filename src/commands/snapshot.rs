@@ -0,0 +1,88 @@
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::database::{self, DbOptions};
+use crate::environment::EnvState;
+use crate::error::Error;
+use crate::runnable::Runnable;
+
+/// Save the database of a running environment to a file or restore it from one.
+pub struct SnapshotCommand {
+    action: SnapshotAction,
+    name: String,
+    /// Directory the snapshots are kept in.
+    dir: PathBuf,
+    state_file: PathBuf,
+    database: DbOptions,
+}
+
+pub enum SnapshotAction {
+    Save,
+    Restore,
+}
+
+/// The file of the snapshot `name`.
+pub fn snapshot_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{}.surql", name))
+}
+
+/// Snapshot names become file names, so they must not leave the directory.
+pub fn parse_name(name: &str) -> Result<String, String> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return Err(format!(
+            "invalid snapshot name `{}`, use e.g. `wishes-1m`",
+            name
+        ));
+    }
+    Ok(name.to_owned())
+}
+
+impl SnapshotCommand {
+    pub fn new(
+        action: SnapshotAction,
+        name: String,
+        dir: PathBuf,
+        state_file: PathBuf,
+        database: DbOptions,
+    ) -> Self {
+        Self {
+            action,
+            name,
+            dir,
+            state_file,
+            database,
+        }
+    }
+}
+
+impl Runnable for SnapshotCommand {
+    async fn run(&mut self) -> Result<(), Error> {
+        let env = EnvState::load(&self.state_file)?;
+        env.check()?;
+        let db = database::connect(env.topology.pd_endpoint(), &self.database).await?;
+        let path = snapshot_path(&self.dir, &self.name);
+        let start = Instant::now();
+        match self.action {
+            SnapshotAction::Save => {
+                std::fs::create_dir_all(&self.dir)?;
+                database::export(&db, &path).await?;
+                println!(
+                    "Saved snapshot {} to {} in {:.1} s.",
+                    self.name,
+                    path.display(),
+                    start.elapsed().as_secs_f64()
+                );
+            }
+            SnapshotAction::Restore => {
+                database::restore(&db, &self.database, &path).await?;
+                println!(
+                    "Restored snapshot {} from {} in {:.1} s.",
+                    self.name,
+                    path.display(),
+                    start.elapsed().as_secs_f64()
+                );
+            }
+        }
+        Ok(())
+    }
+}
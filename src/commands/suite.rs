@@ -0,0 +1,343 @@
+use clap_complete::Shell;
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::commands::case::{CaseNum, CaseOptions, CaseOutcome, TestCase};
+use crate::environment::EnvState;
+use crate::error::Error;
+use crate::junit::{self, JunitCase, TestSuite};
+use crate::results::Overall;
+use crate::run_dir::RunDir;
+use crate::runnable::Runnable;
+
+/// Run several cases one after another against the same playground and server.
+pub struct SuiteCommand {
+    cases: Vec<CaseNum>,
+    /// Not needed when attaching to an environment.
+    executable: Option<PathBuf>,
+    shell: Shell,
+    /// Options for every case. Without `env`, the suite starts its own environment.
+    options: CaseOptions,
+}
+
+/// The sizes of `--sweep n=1000,10000,100000`.
+#[derive(Clone, Debug)]
+pub struct Sweep {
+    pub sizes: Vec<u32>,
+}
+
+impl FromStr for Sweep {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid sweep `{}`, use e.g. `n=1000,10000,100000`", s);
+        let sizes = s
+            .strip_prefix("n=")
+            .ok_or_else(invalid)?
+            .split(',')
+            .map(|n| n.trim().parse::<u32>().map_err(|_| invalid()))
+            .collect::<Result<Vec<_>, _>>()?;
+        if sizes.len() < 2 {
+            return Err(format!("a sweep needs at least two sizes, got `{}`", s));
+        }
+        Ok(Self { sizes })
+    }
+}
+
+/// How one case of the suite ended.
+struct Row {
+    case: CaseNum,
+    outcome: Option<CaseOutcome>,
+    error: Option<Error>,
+}
+
+impl SuiteCommand {
+    pub fn new(
+        cases: Vec<CaseNum>,
+        executable: Option<PathBuf>,
+        shell: Shell,
+        options: CaseOptions,
+    ) -> Self {
+        Self {
+            cases,
+            executable,
+            shell,
+            options,
+        }
+    }
+
+    /// Start the playground and server for the whole suite and stop them on Ctrl+C.
+    /// Returns the state file the cases attach to.
+    fn up(&self) -> Result<PathBuf, Error> {
+        let executable = self
+            .executable
+            .as_deref()
+            .expect("the server executable is required without --env");
+        let run_dir = RunDir::create(&self.options.runs_dir, "suite")?;
+        let state_file = run_dir.file("env.json");
+        let env = EnvState::up(&self.shell, executable, &self.options.playground, &run_dir)?;
+        env.save(&state_file)?;
+        let handler_state_file = state_file.clone();
+        ctrlc::set_handler(move || {
+            // case five may have restarted the server, so read its current pid
+            if let Ok(env) = EnvState::load(&handler_state_file) {
+                env.down();
+            }
+            std::process::exit(1);
+        })
+        .expect("Error setting Ctrl-C handler");
+        Ok(state_file)
+    }
+}
+
+impl Runnable for SuiteCommand {
+    async fn run(&mut self) -> Result<(), Error> {
+        let (state_file, owned) = match &self.options.env {
+            Some(state_file) => (state_file.clone(), false),
+            None => (self.up()?, true),
+        };
+        let mut rows = Vec::new();
+        for (index, case) in self.cases.iter().enumerate() {
+            let mut options = self.options.clone();
+            options.env = Some(state_file.clone());
+            // the suite writes one JUnit file for all cases
+            options.junit = None;
+            // a fresh environment has no data to throw away yet
+            options.wipe = self.options.wipe && (index > 0 || !owned);
+            // the cases must not register the same users
+            options.seed = self
+                .options
+                .seed
+                .map(|seed| seed.wrapping_add(index as u64));
+            let mut test_case = TestCase::new(
+                case.clone(),
+                self.executable.as_deref(),
+                &self.shell,
+                options,
+            );
+            let error = test_case.run().await.err();
+            if let Some(err) = &error {
+                eprintln!("{}", err);
+            }
+            rows.push(Row {
+                case: case.clone(),
+                outcome: test_case.take_outcome(),
+                error,
+            });
+        }
+        if owned {
+            match EnvState::load(&state_file) {
+                Ok(env) => env.down(),
+                Err(err) => eprintln!("Failed to stop the environment of the suite: {}", err),
+            }
+        }
+
+        print!("{}", render_summary(&rows));
+        print!("{}", render_scaling(&rows));
+        let failed = rows.iter().filter(|row| row.error.is_some()).count();
+        let total = rows.len();
+        if let Some(junit) = &self.options.junit {
+            let suites: Vec<TestSuite> = rows.into_iter().map(junit_suite).collect();
+            junit::write(junit, &suites)?;
+            println!("JUnit report written to {}.", junit.display());
+        }
+        match failed {
+            0 => Ok(()),
+            failed => Err(Error::Failed(format!(
+                "{} of {} cases of the suite failed",
+                failed, total
+            ))),
+        }
+    }
+}
+
+fn render_summary(rows: &[Row]) -> String {
+    let mut out = String::new();
+    writeln!(
+        out,
+        "{:<6} {:>8} {:>7} {:>10} {:>9} {:>9} {:>9} {:>9}  {:<6}  description",
+        "case", "requests", "errors", "req/s", "p50 ms", "p99 ms", "max ms", "seconds", "result"
+    )
+    .unwrap();
+    for row in rows {
+        let result = if row.error.is_some() {
+            "FAILED"
+        } else {
+            "passed"
+        };
+        match &row.outcome {
+            Some(outcome) => {
+                let overall = &outcome.results.overall;
+                writeln!(
+                    out,
+                    "{:<6} {:>8} {:>7} {:>10.1} {:>9.2} {:>9.2} {:>9.2} {:>9.2}  {:<6}  {}",
+                    row.case.name(),
+                    overall.count,
+                    overall.errors,
+                    overall.throughput,
+                    overall.latency.p50_ms,
+                    overall.latency.p99_ms,
+                    overall.latency.max_ms,
+                    outcome.results.elapsed_ms / 1000.0,
+                    result,
+                    row.case.description()
+                )
+                .unwrap();
+            }
+            None => {
+                writeln!(
+                    out,
+                    "{:<6} {:>8} {:>7} {:>10} {:>9} {:>9} {:>9} {:>9}  {:<6}  {}",
+                    row.case.name(),
+                    "-",
+                    "-",
+                    "-",
+                    "-",
+                    "-",
+                    "-",
+                    "-",
+                    result,
+                    row.case.description()
+                )
+                .unwrap();
+            }
+        }
+    }
+    for row in rows {
+        if let Some(outcome) = &row.outcome {
+            writeln!(
+                out,
+                "Results of case {}: {}",
+                row.case.name(),
+                outcome.run_dir.join("results.json").display()
+            )
+            .unwrap();
+        }
+    }
+    out
+}
+
+/// How throughput and latency change with the size, for every case run at several sizes.
+/// The exponent k of `p50 ~ n^k` is estimated from each step to the next one, i.e. 1 means
+/// the latency grows linearly with n.
+fn render_scaling(rows: &[Row]) -> String {
+    let mut out = String::new();
+    // in the order the cases first ran, e.g. `one:10 two:10 one:100` has one and two
+    let mut names: Vec<&str> = Vec::new();
+    for row in rows {
+        if !names.contains(&row.case.name()) {
+            names.push(row.case.name());
+        }
+    }
+    for name in names {
+        let mut runs: Vec<(u32, &Overall)> = rows
+            .iter()
+            .filter(|row| row.case.name() == name)
+            .filter_map(|row| {
+                let outcome = row.outcome.as_ref()?;
+                Some((row.case.size(), &outcome.results.overall))
+            })
+            .collect();
+        runs.sort_by_key(|(n, _)| *n);
+        runs.dedup_by_key(|(n, _)| *n);
+        if runs.len() < 2 {
+            continue;
+        }
+        writeln!(out, "Scaling of case {}:", name).unwrap();
+        writeln!(
+            out,
+            "{:>10} {:>10} {:>9} {:>9} {:>9} {:>9}",
+            "n", "req/s", "p50 ms", "p99 ms", "max ms", "p50 ~ n^k"
+        )
+        .unwrap();
+        let mut previous: Option<(u32, &Overall)> = None;
+        for (n, overall) in &runs {
+            let exponent = previous
+                .and_then(|(previous_n, previous)| {
+                    exponent(
+                        previous_n,
+                        previous.latency.p50_ms,
+                        *n,
+                        overall.latency.p50_ms,
+                    )
+                })
+                .map(|k| format!("{:.2}", k))
+                .unwrap_or("-".to_owned());
+            writeln!(
+                out,
+                "{:>10} {:>10.1} {:>9.2} {:>9.2} {:>9.2} {:>9}",
+                n,
+                overall.throughput,
+                overall.latency.p50_ms,
+                overall.latency.p99_ms,
+                overall.latency.max_ms,
+                exponent
+            )
+            .unwrap();
+            previous = Some((*n, overall));
+        }
+    }
+    out
+}
+
+/// k of `latency ~ n^k` between two points, `None` where it is undefined.
+fn exponent(n1: u32, latency1: f64, n2: u32, latency2: f64) -> Option<f64> {
+    if n1 == 0 || n2 <= n1 || latency1 <= 0.0 || latency2 <= 0.0 {
+        return None;
+    }
+    Some((latency2 / latency1).ln() / (n2 as f64 / n1 as f64).ln())
+}
+
+/// The JUnit suite of a case, or a failed one if the case did not get to its checks.
+fn junit_suite(row: Row) -> TestSuite {
+    match row.outcome {
+        Some(outcome) => outcome.junit,
+        None => TestSuite {
+            name: format!("case {}", row.case.name()),
+            time_s: 0.0,
+            cases: vec![JunitCase {
+                name: format!("case {}: {}", row.case.name(), row.case.description()),
+                classname: format!("testing.case.{}", row.case.name()),
+                time_s: 0.0,
+                failure: row.error.as_ref().map(Error::to_string),
+            }],
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sweeps() {
+        let sweep: Sweep = "n=1000,10000,100000".parse().unwrap();
+        assert_eq!(sweep.sizes, [1000, 10000, 100000]);
+        let sweep: Sweep = "n=10, 20".parse().unwrap();
+        assert_eq!(sweep.sizes, [10, 20]);
+    }
+
+    #[test]
+    fn rejects_invalid_sweeps() {
+        for invalid in [
+            "1000,10000",
+            "m=1000,10000",
+            "n=1000",
+            "n=",
+            "n=1000,ten",
+            "n=1000,,10000",
+            "n=-1,10",
+        ] {
+            assert!(invalid.parse::<Sweep>().is_err(), "`{}`", invalid);
+        }
+    }
+
+    #[test]
+    fn estimates_the_scaling_exponent() {
+        assert!((exponent(1000, 2.0, 10000, 20.0).unwrap() - 1.0).abs() < 1e-9);
+        assert!(exponent(1000, 2.0, 10000, 2.0).unwrap().abs() < 1e-9);
+        assert_eq!(exponent(1000, 0.0, 10000, 2.0), None);
+        assert_eq!(exponent(1000, 2.0, 1000, 3.0), None);
+    }
+}
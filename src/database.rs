@@ -0,0 +1,115 @@
+use std::path::Path;
+use std::time::Duration;
+
+use surrealdb::engine::local::{Db, TiKv};
+use surrealdb::Surreal;
+
+use crate::commands::case::{User, Wish};
+use crate::error::Error;
+
+/// How long connecting to the TiKV cluster may take.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where the server keeps its data, to access it directly in the TiKV cluster.
+#[derive(Clone, Debug)]
+pub struct DbOptions {
+    pub namespace: String,
+    pub database: String,
+}
+
+/// Users and wishes removed by [`remove_users`].
+#[derive(Debug, Default)]
+pub struct Removed {
+    pub users: u64,
+    pub wishes: u64,
+}
+
+/// Connect to the data of the server in the cluster of the PD at `pd_endpoint`.
+pub async fn connect(pd_endpoint: &str, options: &DbOptions) -> Result<Surreal<Db>, Error> {
+    let db = tokio::time::timeout(CONNECT_TIMEOUT, Surreal::new::<TiKv>(pd_endpoint))
+        .await
+        .map_err(|_| {
+            Error::Invalid(format!(
+                "cannot connect to the TiKV cluster of {} within {} s",
+                pd_endpoint,
+                CONNECT_TIMEOUT.as_secs()
+            ))
+        })??;
+    db.use_ns(&options.namespace)
+        .use_db(&options.database)
+        .await?;
+    Ok(db)
+}
+
+/// Remove the users whose names start with `prefix` and all their wishes, except the users
+/// named in `keep`.
+pub async fn remove_users(
+    db: &Surreal<Db>,
+    prefix: &str,
+    keep: &[String],
+) -> Result<Removed, Error> {
+    let mut response = db
+        .query("LET $users = (SELECT VALUE id FROM user WHERE string::starts_with(name, $prefix) AND name NOTINSIDE $keep)")
+        .query(
+            "RETURN (SELECT count() FROM wish WHERE created_by IN $users GROUP ALL)[0].count ?? 0",
+        )
+        .query("RETURN array::len($users)")
+        .query("DELETE wish WHERE created_by IN $users")
+        .query("DELETE user WHERE id IN $users")
+        .bind(("prefix", prefix.to_owned()))
+        .bind(("keep", keep.to_vec()))
+        .await?
+        .check()?;
+    Ok(Removed {
+        wishes: response.take::<Option<u64>>(1)?.unwrap_or_default(),
+        users: response.take::<Option<u64>>(2)?.unwrap_or_default(),
+    })
+}
+
+/// Insert `users`. Their passwords are hashed with argon2 unless `plain_passwords`, as the
+/// server has to accept them on login.
+pub async fn insert_users(
+    db: &Surreal<Db>,
+    users: Vec<User>,
+    plain_passwords: bool,
+) -> Result<(), Error> {
+    db.query("INSERT INTO user (SELECT *, IF $plain THEN pass ELSE crypto::argon2::generate(pass) END AS pass FROM $users) RETURN NONE")
+        .bind(("users", users))
+        .bind(("plain", plain_passwords))
+        .await?
+        .check()?;
+    Ok(())
+}
+
+pub async fn insert_wishes(db: &Surreal<Db>, wishes: Vec<Wish>) -> Result<(), Error> {
+    db.query("INSERT INTO wish $wishes RETURN NONE")
+        .bind(("wishes", wishes))
+        .await?
+        .check()?;
+    Ok(())
+}
+
+/// Write all definitions and records of the database to `path`.
+pub async fn export(db: &Surreal<Db>, path: &Path) -> Result<(), Error> {
+    db.export(path).await?;
+    Ok(())
+}
+
+/// Replace the database with the export at `path`, so nothing from before is left.
+pub async fn restore(db: &Surreal<Db>, options: &DbOptions, path: &Path) -> Result<(), Error> {
+    if !path.is_file() {
+        return Err(Error::Invalid(format!(
+            "there is no snapshot {}, save one with `testing snapshot save`",
+            path.display()
+        )));
+    }
+    db.query(format!(
+        "REMOVE DATABASE IF EXISTS `{}`",
+        options.database.replace('`', "")
+    ))
+    .await?
+    .check()?;
+    db.use_db(&options.database).await?;
+    db.import(path).await?;
+    Ok(())
+}
@@ -0,0 +1,205 @@
+use clap_complete::Shell;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::error::Error;
+use crate::playground::{self, Playground, PlaygroundOptions, Topology};
+use crate::run_dir::RunDir;
+use crate::server;
+
+/// How long `down` waits for the processes to exit.
+const STOP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A playground and server started by `testing env up`, which cases can attach to.
+/// Stored as JSON in the state file.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EnvState {
+    /// Directory with the tiup and server logs of the environment.
+    pub run_dir: PathBuf,
+    pub executable: PathBuf,
+    pub playground: PlaygroundOptions,
+    pub tiup_pid: u32,
+    pub server_pid: u32,
+    pub topology: Topology,
+    /// Components a case started again after killing them, which tiup does not stop.
+    #[serde(default)]
+    pub restarted: Vec<u32>,
+    /// Unix timestamp (seconds) of the start.
+    pub started_at: u64,
+}
+
+impl EnvState {
+    /// Start a playground and the server, which keep running after we exit.
+    pub fn up(
+        shell: &Shell,
+        executable: &Path,
+        options: &PlaygroundOptions,
+        run_dir: &RunDir,
+    ) -> Result<Self, Error> {
+        let mut playground = Playground::start(shell, options, run_dir)?;
+        thread::sleep(Duration::from_secs(1));
+        let pd_endpoint = playground.topology().pd_endpoint().to_owned();
+        let server = match server::start(executable, &pd_endpoint, run_dir) {
+            Ok(server) => server,
+            Err(err) => {
+                playground.stop();
+                return Err(err);
+            }
+        };
+        Ok(Self {
+            run_dir: run_dir.path().to_path_buf(),
+            executable: executable.to_path_buf(),
+            playground: options.clone(),
+            tiup_pid: playground.pid(),
+            server_pid: server.id(),
+            topology: playground.topology().clone(),
+            restarted: Vec::new(),
+            started_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        })
+    }
+
+    /// Stop the server and the playground and wait until they are gone.
+    pub fn down(&self) {
+        println!("Stopping SurrealDB server!");
+        self.stop_server();
+        println!("Stopping tiup cluster!");
+        if !playground::interrupt(&self.playground.tag) {
+            signal(self.tiup_pid, "-2");
+        }
+        if !wait_for_exit(self.tiup_pid, STOP_TIMEOUT) {
+            eprintln!(
+                "tiup ({}) is still running after {} s.",
+                self.tiup_pid,
+                STOP_TIMEOUT.as_secs()
+            );
+        }
+        for &pid in &self.restarted {
+            signal(pid, "-9");
+            wait_for_exit(pid, STOP_TIMEOUT);
+        }
+    }
+
+    /// Take over a component a case started again in place of the process `replaced`. It
+    /// keeps running after the case and is stopped by `down`.
+    pub fn adopt(&mut self, replaced: u32, component: Child) {
+        self.topology.replace_pid(replaced, component.id());
+        self.restarted.retain(|&pid| pid != replaced);
+        self.restarted.push(component.id());
+    }
+
+    /// Start the server again, e.g. to check that data survives it.
+    pub fn restart_server(&mut self) -> Result<(), Error> {
+        println!("Stopping SurrealDB server!");
+        self.stop_server();
+        let server = server::start(
+            &self.executable,
+            self.topology.pd_endpoint(),
+            &self.log_dir(),
+        )?;
+        self.server_pid = server.id();
+        Ok(())
+    }
+
+    /// Throw away all data by starting the environment again from scratch.
+    pub fn wipe(&mut self, shell: &Shell) -> Result<(), Error> {
+        println!("Wiping the data of the environment...");
+        self.down();
+        clean(&self.playground.tag);
+        *self = Self::up(shell, &self.executable, &self.playground, &self.log_dir())?;
+        Ok(())
+    }
+
+    /// Fail unless the server and the playground are still running.
+    pub fn check(&self) -> Result<(), Error> {
+        for (name, pid) in [("server", self.server_pid), ("tiup", self.tiup_pid)] {
+            if !running(pid) {
+                return Err(Error::Invalid(format!(
+                    "the {} ({}) of the environment is not running any more, restart it with `testing env down` and `testing env up`",
+                    name, pid
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn log_dir(&self) -> RunDir {
+        RunDir::open(self.run_dir.clone())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let file = std::fs::File::open(path).map_err(|err| {
+            Error::Invalid(format!(
+                "cannot read the environment state {}, start one with `testing env up`: {}",
+                path.display(),
+                err
+            ))
+        })?;
+        Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = std::fs::File::create(path)?;
+        serde_json::to_writer_pretty(std::io::BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    fn stop_server(&self) {
+        signal(self.server_pid, "-9");
+        wait_for_exit(self.server_pid, STOP_TIMEOUT);
+    }
+}
+
+/// Whether a process with `pid` exists and has not exited yet. Exited children we have
+/// not waited for are still listed, as zombies.
+pub fn running(pid: u32) -> bool {
+    Command::new("ps")
+        .args(["-o", "stat=", "-p", &pid.to_string()])
+        .output()
+        .is_ok_and(|output| {
+            let stat = String::from_utf8_lossy(&output.stdout);
+            output.status.success() && !stat.trim().is_empty() && !stat.trim().starts_with('Z')
+        })
+}
+
+/// Remove the data tiup keeps for the playground tagged `tag`.
+pub fn clean(tag: &str) {
+    println!("Removing the data of playground {}.", tag);
+    match Command::new("tiup").arg("clean").arg(tag).output() {
+        Ok(output) if !output.status.success() => eprintln!(
+            "Failed to remove the data of playground {}: {}",
+            tag,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+        Ok(_) => {}
+        Err(err) => eprintln!("Failed to run tiup clean: {}", err),
+    }
+}
+
+fn signal(pid: u32, signal: &str) {
+    let _ = Command::new("kill")
+        .arg(signal)
+        .arg(pid.to_string())
+        .stderr(Stdio::null())
+        .status();
+}
+
+/// Returns `false` if the process is still running after `timeout`.
+fn wait_for_exit(pid: u32, timeout: Duration) -> bool {
+    let start = Instant::now();
+    while running(pid) {
+        if start.elapsed() > timeout {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(200));
+    }
+    true
+}
@@ -0,0 +1,53 @@
+use std::fmt;
+
+/// Everything that can make a command fail.
+#[derive(Debug)]
+pub enum Error {
+    Request(reqwest::Error),
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    /// Boxed, as it is much larger than the others.
+    Database(Box<surrealdb::Error>),
+    Invalid(String),
+    /// The command ran, but the run did not pass its checks.
+    Failed(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Request(err) => write!(f, "request failed: {}", err),
+            Error::Io(err) => write!(f, "i/o error: {}", err),
+            Error::Json(err) => write!(f, "invalid json: {}", err),
+            Error::Database(err) => write!(f, "database error: {}", err),
+            Error::Invalid(msg) => write!(f, "{}", msg),
+            Error::Failed(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        Error::Request(err)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Json(err)
+    }
+}
+
+impl From<surrealdb::Error> for Error {
+    fn from(err: surrealdb::Error) -> Self {
+        Error::Database(Box::new(err))
+    }
+}
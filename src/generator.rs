@@ -0,0 +1,578 @@
+use rand::distributions::Alphanumeric;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+/// Random names, passwords and wishes, reproducible from the seed.
+pub struct Generator {
+    seed: u64,
+    content: ContentOptions,
+    rng: Mutex<StdRng>,
+}
+
+/// What the content of generated wishes looks like.
+#[derive(Clone, Debug, Default)]
+pub struct ContentOptions {
+    pub length: Length,
+    pub text: Text,
+}
+
+/// Distribution of the number of characters of a wish, e.g. `fixed:24`, `uniform:10-200`,
+/// `normal:80,20` or `file:wishes.txt` for the lengths of the lines of a file.
+#[derive(Clone, Debug)]
+pub enum Length {
+    Fixed(usize),
+    Uniform(usize, usize),
+    Normal {
+        mean: f64,
+        std_dev: f64,
+    },
+    Sample {
+        path: String,
+        lengths: Arc<Vec<usize>>,
+    },
+}
+
+/// The characters of a wish.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Text {
+    /// `wish` and random letters and digits, as always.
+    #[default]
+    Ascii,
+    /// Random characters of several scripts and emoji, most of them several bytes in UTF-8.
+    Unicode,
+    /// English sentences from templates.
+    Sentences,
+}
+
+/// Which user each read goes to, e.g. `uniform`, `zipf:1.1` for the first users being read
+/// far more often than the last ones, or `hotspot:10%,90%` for 90 % of the reads going to
+/// 10 % of the users.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Access {
+    #[default]
+    Uniform,
+    Zipf(f64),
+    Hotspot {
+        users: f64,
+        reads: f64,
+    },
+}
+
+/// How the wishes are shared by the users, e.g. `equal`, `random` for each wish going to a
+/// random user, or `power:1.5` for the first users having far more wishes than the last ones.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Distribution {
+    #[default]
+    Equal,
+    Random,
+    PowerLaw(f64),
+}
+
+/// Characters of [`Text::Unicode`]: accented Latin, Greek, Cyrillic, Arabic, Hebrew,
+/// Devanagari, CJK, Hangul and emoji.
+const UNICODE: &[char] = &[
+    'é', 'ü', 'ñ', 'ø', 'ß', 'ç', 'λ', 'Ω', 'π', 'ж', 'Я', 'ш', 'ع', 'ب', 'ש', 'א', 'क', 'ह', '中',
+    '文', '愿', '望', '日', '本', '한', '글', '🎁', '🎄', '⭐', '🧸', '🚲', '📚', '🎮', '❤', ' ',
+];
+
+const TEMPLATES: &[&str] = &[
+    "I wish for a {adjective} {noun}.",
+    "Please bring me a {noun} for {occasion}.",
+    "My {relative} would love a {adjective} {noun}.",
+    "Could I get a {noun} and a {noun}?",
+    "This year I only want a {adjective} {noun} for {occasion}.",
+    "A {noun} that is {adjective} would make my {relative} very happy.",
+];
+const ADJECTIVES: &[&str] = &[
+    "red", "small", "wooden", "shiny", "warm", "fast", "quiet", "colorful", "soft", "new",
+];
+const NOUNS: &[&str] = &[
+    "bicycle",
+    "teddy bear",
+    "book",
+    "puzzle",
+    "scarf",
+    "train set",
+    "guitar",
+    "telescope",
+    "board game",
+    "pair of skates",
+];
+const OCCASIONS: &[&str] = &[
+    "Christmas",
+    "my birthday",
+    "the holidays",
+    "winter",
+    "the new year",
+];
+const RELATIVES: &[&str] = &[
+    "sister",
+    "brother",
+    "grandma",
+    "grandpa",
+    "dog",
+    "best friend",
+];
+
+impl Generator {
+    /// A generator with `seed`, or with a random one to print and reuse.
+    pub fn new(seed: Option<u64>, content: ContentOptions) -> Self {
+        let seed = seed.unwrap_or_else(rand::random);
+        Self {
+            seed,
+            content,
+            rng: Mutex::new(StdRng::seed_from_u64(seed)),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn content(&self) -> &ContentOptions {
+        &self.content
+    }
+
+    pub fn chars(&self, n: usize) -> String {
+        alphanumeric(
+            &mut self.rng.lock().expect("failed to lock the generator"),
+            n,
+        )
+    }
+
+    /// `prefix` is `test` and the tag of the run, or `seed` and the tag of the seeded data.
+    pub fn username(&self, prefix: &str) -> String {
+        format!("{}{}", prefix, self.chars(14))
+    }
+
+    pub fn password(&self) -> String {
+        format!("pass{}", self.chars(20))
+    }
+
+    pub fn wish(&self) -> String {
+        let mut rng = self.rng.lock().expect("failed to lock the generator");
+        let length = self.content.length.sample(&mut rng);
+        match self.content.text {
+            Text::Ascii => format!("wish{}", alphanumeric(&mut rng, length.saturating_sub(4)))
+                .chars()
+                .take(length)
+                .collect(),
+            Text::Unicode => (0..length)
+                .map(|_| *UNICODE.choose(&mut *rng).expect("characters are not empty"))
+                .collect(),
+            Text::Sentences => sentences(&mut rng, length),
+        }
+    }
+
+    /// How many of `total` wishes each of `users` users gets. The counts always add up to
+    /// `total`.
+    pub fn shares(&self, distribution: Distribution, total: u64, users: usize) -> Vec<u64> {
+        if users == 0 {
+            return Vec::new();
+        }
+        match distribution {
+            Distribution::Equal => apportion(&vec![1.0; users], total),
+            Distribution::Random => {
+                let mut rng = self.rng.lock().expect("failed to lock the generator");
+                let mut shares = vec![0; users];
+                for _ in 0..total {
+                    shares[rng.gen_range(0..users)] += 1;
+                }
+                shares
+            }
+            Distribution::PowerLaw(exponent) => {
+                let weights: Vec<f64> = (1..=users)
+                    .map(|k| 1.0 / (k as f64).powf(exponent))
+                    .collect();
+                apportion(&weights, total)
+            }
+        }
+    }
+
+    /// The indices of the users `n` reads go to, drawn from `access` over `users` users.
+    pub fn accesses(&self, access: Access, users: usize, n: usize) -> Vec<usize> {
+        let mut rng = self.rng.lock().expect("failed to lock the generator");
+        if users == 0 {
+            return Vec::new();
+        }
+        match access {
+            Access::Uniform => (0..n).map(|_| rng.gen_range(0..users)).collect(),
+            Access::Zipf(exponent) => {
+                // the user of rank k is read with a probability proportional to 1 / k^exponent
+                let mut cdf: Vec<f64> = (1..=users)
+                    .scan(0.0, |sum, k| {
+                        *sum += 1.0 / (k as f64).powf(exponent);
+                        Some(*sum)
+                    })
+                    .collect();
+                let total = *cdf.last().expect("there are users");
+                cdf.iter_mut().for_each(|p| *p /= total);
+                (0..n)
+                    .map(|_| {
+                        let p: f64 = rng.gen();
+                        cdf.partition_point(|&q| q < p).min(users - 1)
+                    })
+                    .collect()
+            }
+            Access::Hotspot {
+                users: hot_users,
+                reads,
+            } => {
+                let hot = ((users as f64 * hot_users).round() as usize).clamp(1, users);
+                (0..n)
+                    .map(|_| {
+                        if hot == users || rng.gen_bool(reads) {
+                            rng.gen_range(0..hot)
+                        } else {
+                            rng.gen_range(hot..users)
+                        }
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// `total` split in proportion to `weights`, the rest of the rounding going to the largest
+/// fractions and then to the first ones.
+fn apportion(weights: &[f64], total: u64) -> Vec<u64> {
+    let sum: f64 = weights.iter().sum();
+    let quotas: Vec<f64> = weights
+        .iter()
+        .map(|weight| total as f64 * weight / sum)
+        .collect();
+    let mut shares: Vec<u64> = quotas.iter().map(|quota| quota.floor() as u64).collect();
+    let mut order: Vec<usize> = (0..weights.len()).collect();
+    order.sort_by(|&a, &b| {
+        (quotas[b] - quotas[b].floor()).total_cmp(&(quotas[a] - quotas[a].floor()))
+    });
+    let rest = total.saturating_sub(shares.iter().sum());
+    for &i in order.iter().cycle().take(rest as usize) {
+        shares[i] += 1;
+    }
+    shares
+}
+
+/// `n` random characters that do not depend on any seed, e.g. to tag a run.
+pub fn random_tag(n: usize) -> String {
+    alphanumeric(&mut StdRng::from_entropy(), n)
+}
+
+fn alphanumeric(rng: &mut StdRng, n: usize) -> String {
+    rng.sample_iter(&Alphanumeric)
+        .take(n)
+        .map(char::from)
+        .collect()
+}
+
+/// Sentences from the templates until there are `length` characters.
+fn sentences(rng: &mut StdRng, length: usize) -> String {
+    let mut text = String::new();
+    while text.chars().count() < length {
+        if !text.is_empty() {
+            text.push(' ');
+        }
+        let mut sentence = TEMPLATES
+            .choose(rng)
+            .expect("templates are not empty")
+            .to_string();
+        for (placeholder, words) in [
+            ("{adjective}", ADJECTIVES),
+            ("{noun}", NOUNS),
+            ("{occasion}", OCCASIONS),
+            ("{relative}", RELATIVES),
+        ] {
+            while sentence.contains(placeholder) {
+                let word = words.choose(rng).expect("words are not empty");
+                sentence = sentence.replacen(placeholder, word, 1);
+            }
+        }
+        text.push_str(&sentence);
+    }
+    text.chars()
+        .take(length)
+        .collect::<String>()
+        .trim_end()
+        .to_owned()
+}
+
+impl Length {
+    /// At least one character, as servers tend to reject empty wishes.
+    fn sample(&self, rng: &mut StdRng) -> usize {
+        let length = match self {
+            Length::Fixed(length) => *length,
+            Length::Uniform(min, max) => rng.gen_range(*min..=*max),
+            Length::Normal { mean, std_dev } => {
+                // Box-Muller transform
+                let u1: f64 = 1.0 - rng.gen::<f64>();
+                let u2: f64 = rng.gen();
+                let z = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+                (mean + std_dev * z).round().max(0.0) as usize
+            }
+            Length::Sample { lengths, .. } => *lengths.choose(rng).expect("samples are not empty"),
+        };
+        length.max(1)
+    }
+}
+
+impl Default for Length {
+    fn default() -> Self {
+        Length::Fixed(24)
+    }
+}
+
+impl fmt::Display for Length {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Length::Fixed(length) => write!(f, "fixed:{}", length),
+            Length::Uniform(min, max) => write!(f, "uniform:{}-{}", min, max),
+            Length::Normal { mean, std_dev } => write!(f, "normal:{},{}", mean, std_dev),
+            Length::Sample { path, .. } => write!(f, "file:{}", path),
+        }
+    }
+}
+
+impl FromStr for Length {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid length `{}`, use `fixed:24`, `uniform:10-200`, `normal:80,20` or `file:<path>`",
+                s
+            )
+        };
+        let (kind, value) = s.split_once(':').unwrap_or(("fixed", s));
+        match kind {
+            "fixed" => Ok(Length::Fixed(value.parse().map_err(|_| invalid())?)),
+            "uniform" => {
+                let (min, max) = value.split_once('-').ok_or_else(invalid)?;
+                let (min, max) = (
+                    min.parse().map_err(|_| invalid())?,
+                    max.parse().map_err(|_| invalid())?,
+                );
+                if min > max {
+                    return Err(invalid());
+                }
+                Ok(Length::Uniform(min, max))
+            }
+            "normal" => {
+                let (mean, std_dev) = value.split_once(',').ok_or_else(invalid)?;
+                let (mean, std_dev): (f64, f64) = (
+                    mean.parse().map_err(|_| invalid())?,
+                    std_dev.parse().map_err(|_| invalid())?,
+                );
+                if !mean.is_finite() || !std_dev.is_finite() || std_dev < 0.0 {
+                    return Err(invalid());
+                }
+                Ok(Length::Normal { mean, std_dev })
+            }
+            "file" => {
+                let content = std::fs::read_to_string(value)
+                    .map_err(|err| format!("cannot read {}: {}", value, err))?;
+                let lengths: Vec<usize> = content
+                    .lines()
+                    .filter(|line| !line.is_empty())
+                    .map(|line| line.chars().count())
+                    .collect();
+                if lengths.is_empty() {
+                    return Err(format!("{} has no lines to take lengths from", value));
+                }
+                Ok(Length::Sample {
+                    path: value.to_owned(),
+                    lengths: Arc::new(lengths),
+                })
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for ContentOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} text of {} characters", self.text, self.length)
+    }
+}
+
+impl fmt::Display for Text {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Text::Ascii => "ascii",
+            Text::Unicode => "unicode",
+            Text::Sentences => "sentences",
+        })
+    }
+}
+
+impl FromStr for Text {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ascii" => Ok(Text::Ascii),
+            "unicode" => Ok(Text::Unicode),
+            "sentences" => Ok(Text::Sentences),
+            _ => Err(format!(
+                "invalid text `{}`, use `ascii`, `unicode` or `sentences`",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Access::Uniform => f.write_str("uniform"),
+            Access::Zipf(exponent) => write!(f, "zipf:{}", exponent),
+            Access::Hotspot { users, reads } => {
+                write!(f, "hotspot:{}%,{}%", users * 100.0, reads * 100.0)
+            }
+        }
+    }
+}
+
+impl FromStr for Access {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid access `{}`, use `uniform`, `zipf:1.1` or `hotspot:10%,90%`",
+                s
+            )
+        };
+        let percent = |value: &str| -> Result<f64, String> {
+            let percent: f64 = value
+                .trim()
+                .trim_end_matches('%')
+                .parse()
+                .map_err(|_| invalid())?;
+            if !(0.0..=100.0).contains(&percent) {
+                return Err(invalid());
+            }
+            Ok(percent / 100.0)
+        };
+        match s.split_once(':') {
+            None if s == "uniform" => Ok(Access::Uniform),
+            Some(("zipf", exponent)) => {
+                let exponent: f64 = exponent.parse().map_err(|_| invalid())?;
+                if !exponent.is_finite() || exponent < 0.0 {
+                    return Err(invalid());
+                }
+                Ok(Access::Zipf(exponent))
+            }
+            Some(("hotspot", value)) => {
+                let (users, reads) = value.split_once(',').ok_or_else(invalid)?;
+                Ok(Access::Hotspot {
+                    users: percent(users)?,
+                    reads: percent(reads)?,
+                })
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for Distribution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Distribution::Equal => f.write_str("equal"),
+            Distribution::Random => f.write_str("random"),
+            Distribution::PowerLaw(exponent) => write!(f, "power:{}", exponent),
+        }
+    }
+}
+
+impl FromStr for Distribution {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid distribution `{}`, use `equal`, `random` or `power:1.5`",
+                s
+            )
+        };
+        match s.split_once(':') {
+            None if s == "equal" => Ok(Distribution::Equal),
+            None if s == "random" => Ok(Distribution::Random),
+            Some(("power", exponent)) => {
+                let exponent: f64 = exponent.parse().map_err(|_| invalid())?;
+                if !exponent.is_finite() || exponent < 0.0 {
+                    return Err(invalid());
+                }
+                Ok(Distribution::PowerLaw(exponent))
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DISTRIBUTIONS: [Distribution; 4] = [
+        Distribution::Equal,
+        Distribution::Random,
+        Distribution::PowerLaw(1.0),
+        Distribution::PowerLaw(2.5),
+    ];
+
+    #[test]
+    fn shares_add_up_to_the_total() {
+        let generator = Generator::new(Some(7), ContentOptions::default());
+        for distribution in DISTRIBUTIONS {
+            for users in [1, 2, 3, 7, 10, 100, 1000] {
+                for total in [0, 1, 5, 10, 99, 1000, 12345] {
+                    let shares = generator.shares(distribution, total, users);
+                    assert_eq!(shares.len(), users, "{} {} {}", distribution, users, total);
+                    assert_eq!(
+                        shares.iter().sum::<u64>(),
+                        total,
+                        "{} over {} users",
+                        distribution,
+                        users
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn no_users_get_no_shares() {
+        let generator = Generator::new(Some(7), ContentOptions::default());
+        for distribution in DISTRIBUTIONS {
+            assert!(generator.shares(distribution, 10, 0).is_empty());
+        }
+    }
+
+    #[test]
+    fn equal_shares_differ_by_at_most_one() {
+        let generator = Generator::new(Some(7), ContentOptions::default());
+        let shares = generator.shares(Distribution::Equal, 10, 3);
+        assert_eq!(shares.iter().sum::<u64>(), 10);
+        assert!(shares.iter().all(|&share| share == 3 || share == 4));
+    }
+
+    #[test]
+    fn power_law_shares_fall_with_the_rank() {
+        let generator = Generator::new(Some(7), ContentOptions::default());
+        let shares = generator.shares(Distribution::PowerLaw(1.0), 1000, 10);
+        assert!(shares.windows(2).all(|pair| pair[0] >= pair[1]));
+        assert!(shares[0] > shares[9]);
+    }
+
+    #[test]
+    fn apportions_by_largest_remainder() {
+        assert_eq!(apportion(&[1.0, 1.0, 1.0], 10), [4, 3, 3]);
+        assert_eq!(apportion(&[3.0, 1.0], 5), [4, 1]);
+        assert_eq!(apportion(&[0.1; 10], 1), [1, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(apportion(&[1.0, 2.0], 0), [0, 0]);
+    }
+}
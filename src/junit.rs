@@ -0,0 +1,83 @@
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::error::Error;
+
+/// A JUnit test suite, one per test case run.
+pub struct TestSuite {
+    pub name: String,
+    pub time_s: f64,
+    pub cases: Vec<JunitCase>,
+}
+
+/// A single JUnit test, i.e. the run itself or one of its assertions.
+pub struct JunitCase {
+    pub name: String,
+    pub classname: String,
+    pub time_s: f64,
+    pub failure: Option<String>,
+}
+
+pub fn write(path: &Path, suites: &[TestSuite]) -> Result<(), Error> {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let tests: usize = suites.iter().map(|suite| suite.cases.len()).sum();
+    let failures: usize = suites.iter().map(TestSuite::failures).sum();
+    writeln!(
+        out,
+        "<testsuites name=\"testing\" tests=\"{}\" failures=\"{}\">",
+        tests, failures
+    )
+    .unwrap();
+    for suite in suites {
+        writeln!(
+            out,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" time=\"{:.3}\">",
+            escape(&suite.name),
+            suite.cases.len(),
+            suite.failures(),
+            suite.time_s
+        )
+        .unwrap();
+        for case in &suite.cases {
+            write!(
+                out,
+                "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+                escape(&case.name),
+                escape(&case.classname),
+                case.time_s
+            )
+            .unwrap();
+            match &case.failure {
+                Some(message) => writeln!(
+                    out,
+                    ">\n      <failure message=\"{}\"/>\n    </testcase>",
+                    escape(message)
+                )
+                .unwrap(),
+                None => out.push_str("/>\n"),
+            }
+        }
+        out.push_str("  </testsuite>\n");
+    }
+    out.push_str("</testsuites>\n");
+    std::fs::write(path, out)?;
+    Ok(())
+}
+
+impl TestSuite {
+    pub fn failures(&self) -> usize {
+        self.cases
+            .iter()
+            .filter(|case| case.failure.is_some())
+            .count()
+    }
+}
+
+/// Escape `text` for XML and HTML, in element content as well as in quoted attributes.
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
@@ -1,18 +1,49 @@
-use std::path::Path;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
+use assertion::{parse_rate, Assertion};
+use chaos::Fault;
 use clap::ValueHint;
 use clap_complete::Shell;
 use commands::{
-    case::{CaseFourArgs, CaseNum, CaseOneArgs, CaseThreeArgs, CaseTwoArgs, TestCase},
+    case::{
+        CaseFiveArgs, CaseFourArgs, CaseNum, CaseOneArgs, CaseOptions, CaseSixArgs, CaseThreeArgs,
+        CaseTwoArgs, TestCase, TokenOptions,
+    },
+    env::{EnvAction, EnvCommand},
     generate::GenerateCommand,
+    replay::ReplayCommand,
+    report::ReportCommand,
+    seed::{SeedCommand, SeedOptions},
+    snapshot::{parse_name, snapshot_path, SnapshotAction, SnapshotCommand},
+    suite::{SuiteCommand, Sweep},
     TestingCommand,
 };
+use database::DbOptions;
+use generator::{Access, ContentOptions, Distribution, Length, Text};
+use playground::PlaygroundOptions;
 use runnable::Runnable;
 
 // Created with help of example from https://github.com/clap-rs/clap/blob/master/clap_complete/examples/exhaustive.rs
 
+mod assertion;
+mod chaos;
 mod commands;
-pub mod runnable;
+mod database;
+mod environment;
+mod error;
+mod generator;
+mod junit;
+mod jwt;
+mod metrics;
+mod monitor;
+mod playground;
+mod results;
+mod run_dir;
+mod runnable;
+mod server;
+mod traffic;
 
 #[tokio::main]
 async fn main() {
@@ -25,78 +56,255 @@ async fn main() {
     let mut command = None;
     let shell;
     if let Some(from_arg) = matches.get_one::<Shell>("shell") {
-        shell = from_arg.clone();
+        shell = *from_arg;
     } else if let Some(from_env) = clap_complete::Shell::from_env() {
         shell = from_env;
     } else {
         panic!("No SHELL provided in environment arguments, please provide the shell argument!");
     }
     if let Some(case_matches) = matches.subcommand_matches("case") {
-        if let Some(executable) = case_matches.get_one::<String>("executable") {
-            let executable = Path::new(executable);
-            if let Some(case_one_matches) = case_matches.subcommand_matches("one") {
-                let n_res = case_one_matches.get_one::<u32>("users");
-                let n;
-                if let Some(res) = n_res {
-                    n = res.clone();
-                } else {
-                    n = 1000;
-                }
-                command = Some(TestingCommand::Case(TestCase::new(
-                    CaseNum::One(CaseOneArgs { n }),
-                    executable,
-                    &shell,
-                )));
-            } else if let Some(case_two_matches) = case_matches.subcommand_matches("two") {
-                let n_wishes = case_two_matches.get_one::<u32>("wishes");
-                let n;
-                if let Some(res) = n_wishes {
-                    n = res.clone();
-                } else {
-                    n = 1000;
-                }
-                command = Some(TestingCommand::Case(TestCase::new(
-                    CaseNum::Two(CaseTwoArgs { wishes: n }),
-                    executable,
-                    &shell,
-                )));
-            } else if let Some(case_two_matches) = case_matches.subcommand_matches("three") {
-                let n_wishes = case_two_matches.get_one::<u32>("wishes");
-                let n;
-                if let Some(res) = n_wishes {
-                    n = res.clone();
-                } else {
-                    n = 1000;
-                }
-                command = Some(TestingCommand::Case(TestCase::new(
-                    CaseNum::Three(CaseThreeArgs { wishes: n }),
-                    executable,
-                    &shell,
-                )));
-            } else if let Some(case_two_matches) = case_matches.subcommand_matches("four") {
-                let n_times = case_two_matches.get_one::<u32>("times");
-                let n;
-                if let Some(res) = n_times {
-                    n = res.clone();
-                } else {
-                    n = 1000;
-                }
-                command = Some(TestingCommand::Case(TestCase::new(
-                    CaseNum::Four(CaseFourArgs { times: n }),
-                    executable,
-                    &shell,
-                )));
+        let executable = case_matches.get_one::<String>("executable").map(Path::new);
+        let options = || CaseOptions {
+            output: case_matches.get_one::<PathBuf>("output").cloned(),
+            raw_samples: case_matches.get_one::<PathBuf>("raw_samples").cloned(),
+            record: case_matches.get_one::<PathBuf>("record").cloned(),
+            faults: case_matches
+                .get_many::<Fault>("chaos")
+                .map(|faults| faults.cloned().collect())
+                .unwrap_or_default(),
+            wipe: case_matches.get_flag("wipe"),
+            ..case_options(case_matches)
+        };
+        // a sweep runs the case at every size as a suite
+        let case_command = |case: CaseNum| match case_matches.get_one::<Sweep>("sweep") {
+            Some(sweep) => TestingCommand::Suite(Box::new(SuiteCommand::new(
+                sweep.sizes.iter().map(|n| case.with_size(*n)).collect(),
+                executable.map(Path::to_path_buf),
+                shell,
+                options(),
+            ))),
+            None => {
+                TestingCommand::Case(Box::new(TestCase::new(case, executable, &shell, options())))
             }
+        };
+        if let Some(case_one_matches) = case_matches.subcommand_matches("one") {
+            let n_res = case_one_matches.get_one::<u32>("users");
+            let n;
+            if let Some(res) = n_res {
+                n = *res;
+            } else {
+                n = 1000;
+            }
+            command = Some(case_command(CaseNum::One(CaseOneArgs { n })));
+        } else if let Some(case_two_matches) = case_matches.subcommand_matches("two") {
+            let n_wishes = case_two_matches.get_one::<u32>("wishes");
+            let n;
+            if let Some(res) = n_wishes {
+                n = *res;
+            } else {
+                n = 1000;
+            }
+            command = Some(case_command(CaseNum::Two(CaseTwoArgs {
+                wishes: n,
+                users: *case_two_matches
+                    .get_one::<u32>("users")
+                    .expect("users have a default"),
+            })));
+        } else if let Some(case_two_matches) = case_matches.subcommand_matches("three") {
+            let n_wishes = case_two_matches.get_one::<u32>("wishes");
+            let n;
+            if let Some(res) = n_wishes {
+                n = *res;
+            } else {
+                n = 1000;
+            }
+            command = Some(case_command(CaseNum::Three(CaseThreeArgs { wishes: n })));
+        } else if let Some(case_two_matches) = case_matches.subcommand_matches("four") {
+            let n_times = case_two_matches.get_one::<u32>("times");
+            let n;
+            if let Some(res) = n_times {
+                n = *res;
+            } else {
+                n = 1000;
+            }
+            command = Some(case_command(CaseNum::Four(CaseFourArgs {
+                times: n,
+                users: *case_two_matches
+                    .get_one::<u32>("users")
+                    .expect("users have a default"),
+                access: *case_two_matches
+                    .get_one::<Access>("access")
+                    .expect("access has a default"),
+            })));
+        } else if let Some(case_five_matches) = case_matches.subcommand_matches("five") {
+            let restart_cluster = case_five_matches.get_flag("restart_cluster");
+            // the steps of a sweep attach to its environment, whose cluster cannot be restarted
+            if restart_cluster && case_matches.get_one::<Sweep>("sweep").is_some() {
+                cli()
+                    .error(
+                        clap::error::ErrorKind::ArgumentConflict,
+                        "the argument '--restart-cluster' cannot be used with '--sweep'",
+                    )
+                    .exit();
+            }
+            let wishes = *case_five_matches
+                .get_one::<u32>("wishes")
+                .expect("wishes have a default");
+            command = Some(case_command(CaseNum::Five(CaseFiveArgs {
+                wishes,
+                users: *case_five_matches
+                    .get_one::<u32>("users")
+                    .expect("users have a default"),
+                restart_cluster,
+            })));
+        } else if let Some(case_six_matches) = case_matches.subcommand_matches("six") {
+            command = Some(case_command(CaseNum::Six(CaseSixArgs {
+                times: *case_six_matches
+                    .get_one::<u32>("times")
+                    .expect("times have a default"),
+                users: *case_six_matches
+                    .get_one::<u32>("users")
+                    .expect("users have a default"),
+                wishes: *case_six_matches
+                    .get_one::<u32>("wishes")
+                    .expect("wishes have a default"),
+            })));
         }
-    } else if let Some(_) = matches.subcommand_matches("generate") {
-        command = Some(TestingCommand::Generate(GenerateCommand::new(shell, cli())));
+    } else if let Some(env_matches) = matches.subcommand_matches("env") {
+        let (action, action_matches) = match env_matches.subcommand() {
+            Some(("up", up_matches)) => (
+                EnvAction::Up {
+                    executable: up_matches
+                        .get_one::<PathBuf>("executable")
+                        .cloned()
+                        .expect("executable is required"),
+                    playground: playground_options(up_matches),
+                    runs_dir: up_matches
+                        .get_one::<PathBuf>("runs_dir")
+                        .cloned()
+                        .expect("runs directory has a default"),
+                },
+                up_matches,
+            ),
+            Some(("down", down_matches)) => (
+                EnvAction::Down {
+                    clean: down_matches.get_flag("clean"),
+                },
+                down_matches,
+            ),
+            Some(("status", status_matches)) => (EnvAction::Status, status_matches),
+            _ => unreachable!("env requires a subcommand"),
+        };
+        command = Some(TestingCommand::Env(EnvCommand::new(
+            action,
+            state_file(action_matches),
+            shell,
+        )));
+    } else if let Some(suite_matches) = matches.subcommand_matches("suite") {
+        let users = suite_matches.get_one::<u32>("users");
+        let cases = suite_matches
+            .get_many::<CaseNum>("cases")
+            .expect("cases have a default")
+            .map(|case| match users {
+                Some(users) => case.with_users(*users),
+                None => case.clone(),
+            })
+            .collect();
+        command = Some(TestingCommand::Suite(Box::new(SuiteCommand::new(
+            cases,
+            suite_matches
+                .get_one::<String>("executable")
+                .map(PathBuf::from),
+            shell,
+            CaseOptions {
+                wipe: suite_matches.get_flag("wipe"),
+                ..case_options(suite_matches)
+            },
+        ))));
+    } else if let Some(seed_matches) = matches.subcommand_matches("seed") {
+        command = Some(TestingCommand::Seed(Box::new(SeedCommand::new(
+            SeedOptions {
+                users: *seed_matches
+                    .get_one::<u32>("users")
+                    .expect("users have a default"),
+                wishes: *seed_matches
+                    .get_one::<u64>("wishes")
+                    .expect("wishes have a default"),
+                batch_size: *seed_matches
+                    .get_one::<u32>("batch_size")
+                    .expect("batch size has a default") as usize,
+                plain_passwords: seed_matches.get_flag("plain_passwords"),
+                content: content_options(seed_matches),
+                distribution: distribution(seed_matches),
+            },
+            state_file(seed_matches),
+            db_options(seed_matches),
+            seed_matches
+                .get_one::<PathBuf>("output")
+                .cloned()
+                .expect("output has a default"),
+            seed_matches.get_one::<u64>("seed").copied(),
+        ))));
+    } else if let Some(snapshot_matches) = matches.subcommand_matches("snapshot") {
+        let (action, action_matches) = match snapshot_matches.subcommand() {
+            Some(("save", save_matches)) => (SnapshotAction::Save, save_matches),
+            Some(("restore", restore_matches)) => (SnapshotAction::Restore, restore_matches),
+            _ => unreachable!("snapshot requires a subcommand"),
+        };
+        command = Some(TestingCommand::Snapshot(Box::new(SnapshotCommand::new(
+            action,
+            action_matches
+                .get_one::<String>("name")
+                .cloned()
+                .expect("name is required"),
+            snapshots_dir(action_matches),
+            state_file(action_matches),
+            db_options(action_matches),
+        ))));
+    } else if matches.subcommand_matches("generate").is_some() {
+        command = Some(TestingCommand::Generate(Box::new(GenerateCommand::new(
+            shell,
+            cli(),
+        ))));
+    } else if let Some(report_matches) = matches.subcommand_matches("report") {
+        let results = report_matches
+            .get_many::<PathBuf>("results")
+            .expect("results are required")
+            .cloned()
+            .collect();
+        let html = report_matches.get_one::<PathBuf>("html").cloned();
+        command = Some(TestingCommand::Report(ReportCommand::new(results, html)));
+    } else if let Some(replay_matches) = matches.subcommand_matches("replay") {
+        command = Some(TestingCommand::Replay(Box::new(ReplayCommand::new(
+            replay_matches
+                .get_one::<PathBuf>("traffic")
+                .cloned()
+                .expect("traffic is required"),
+            replay_matches
+                .get_one::<String>("url")
+                .cloned()
+                .expect("url has a default"),
+            *replay_matches
+                .get_one::<f64>("speed")
+                .expect("speed has a default"),
+            Duration::from_secs(
+                *replay_matches
+                    .get_one::<u64>("request_timeout")
+                    .expect("request timeout has a default"),
+            ),
+            replay_matches
+                .get_one::<PathBuf>("runs_dir")
+                .cloned()
+                .expect("runs directory has a default"),
+            replay_matches.get_one::<PathBuf>("output").cloned(),
+        ))));
     }
 
     if let Some(mut command) = command {
-        command
-            .run()
-            .await
-            .expect("{command} has not been executed successfully!");
+        if let Err(err) = command.run().await {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
     } else {
         eprintln!("Cannot recognize subcommand or no subcommands present.");
         return;
@@ -115,12 +323,40 @@ fn cli() -> clap::Command {
             clap::Command::new("generate").about("Generate shell completions"),
             clap::Command::new("case")
                 .about("Run a test case")
-                .args([clap::Arg::new("executable")
-                    .required(true)
-                    .long("surrealdb-executable")
-                    .short('e')
-                    .help("The path to the executable starting the surrealdb server")
-                    .value_hint(ValueHint::FilePath)])
+                .args(case_args())
+                .args([
+                    clap::Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .help("Write the results of the run as JSON to this file, e.g. for `testing report`.")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .value_hint(ValueHint::FilePath),
+                    clap::Arg::new("raw_samples")
+                        .long("raw-samples")
+                        .help("Write one CSV row per request (operation, start offset, latency, HTTP status, bytes, error) to this file.")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .value_hint(ValueHint::FilePath),
+                    clap::Arg::new("record")
+                        .long("record")
+                        .help("Write every request and response (method, path, headers, body, status, timing) as JSON lines to this file, to replay them with `testing replay`.")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .value_hint(ValueHint::FilePath),
+                    clap::Arg::new("chaos")
+                        .long("chaos")
+                        .action(clap::ArgAction::Append)
+                        .help("Inject a fault into the cluster during the measured phase: `<pause|kill>:<pd|tikv>[.<index>]@<at>[+<duration>]`. E.g. `pause:tikv.1@10s+5s` sends SIGSTOP to the second TiKV 10 s into the run and SIGCONT 5 s later, `kill:pd@30s+20s` sends SIGKILL to the first PD and restarts it 20 s later. Can be given multiple times.")
+                        .value_parser(|s: &str| s.parse::<Fault>()),
+                    clap::Arg::new("sweep")
+                        .long("sweep")
+                        .conflicts_with_all(["output", "raw_samples", "record"])
+                        .help("Run the case once per size, e.g. `n=1000,10000,100000`, against one playground and report how throughput and latency scale.")
+                        .value_parser(|s: &str| s.parse::<Sweep>()),
+                    clap::Arg::new("wipe")
+                        .long("wipe")
+                        .requires("env")
+                        .help("Throw away all data of the environment before the case, by starting it again from scratch.")
+                        .action(clap::ArgAction::SetTrue),
+                ])
                 .subcommands([
                     clap::Command::new("one")
                         .about("Run test case one: Register n users.")
@@ -133,15 +369,16 @@ fn cli() -> clap::Command {
                                 .default_value("1000"),
                         ),
                     clap::Command::new("two")
-                        .about("Run test case one: Register 10 users and create n/10 wishes.")
-                        .arg(
+                        .about("Run test case two: Register u users and create n wishes between them.")
+                        .args([
                             clap::Arg::new("wishes")
                                 .short('n')
                                 .long("n-wishes")
                                 .help("Number of wishes to create. Defaults to 1000.")
                                 .value_parser(clap::value_parser!(u32))
                                 .default_value("1000"),
-                        ),
+                            users_arg(),
+                        ]),
                     clap::Command::new("three")
                         .about("Run test case three: Get n wishes with one request.")
                         .arg(
@@ -161,7 +398,515 @@ fn cli() -> clap::Command {
                                 .help("Number of times to get the wish. Defaults to 1000.")
                                 .value_parser(clap::value_parser!(u32))
                                 .default_value("1000"),
-                        ),
+                        )
+                        .args([
+                            clap::Arg::new("users")
+                                .short('u')
+                                .long("users")
+                                .help("Number of users with one wish each to spread the reads over. Defaults to 1.")
+                                .value_parser(clap::value_parser!(u32).range(1..))
+                                .default_value("1"),
+                            clap::Arg::new("access")
+                                .long("access")
+                                .help("Which user each read goes to: `uniform`, `zipf:<exponent>`, e.g. `zipf:1.1`, or `hotspot:<users>%,<reads>%`, e.g. `hotspot:10%,90%` for 90 % of the reads going to 10 % of the users.")
+                                .value_parser(|s: &str| s.parse::<Access>())
+                                .default_value("uniform"),
+                        ]),
+                    clap::Command::new("five")
+                        .about("Run test case five: Create n wishes of u users, restart the server and check that all of them survived.")
+                        .args([
+                            users_arg(),
+                            clap::Arg::new("wishes")
+                                .short('n')
+                                .long("n-wishes")
+                                .help("Number of wishes to create. Defaults to 1000.")
+                                .value_parser(clap::value_parser!(u32))
+                                .default_value("1000"),
+                            clap::Arg::new("restart_cluster")
+                                .long("restart-cluster")
+                                .help("Restart the tiup cluster too. Its data is kept, as the playground has a tag. Not possible with `--sweep` or `--env`, whose cluster is shared.")
+                                .action(clap::ArgAction::SetTrue),
+                        ]),
+                    clap::Command::new("six")
+                        .about("Run test case six: Many users get their own wishes concurrently, n times in total.")
+                        .args([
+                            clap::Arg::new("times")
+                                .short('n')
+                                .long("n-times")
+                                .help("Number of times to get the wishes, shared by all users. Defaults to 1000.")
+                                .value_parser(clap::value_parser!(u32))
+                                .default_value("1000"),
+                            clap::Arg::new("users")
+                                .short('u')
+                                .long("users")
+                                .help("Number of users reading at the same time, each with their own login. Defaults to 100.")
+                                .value_parser(clap::value_parser!(u32).range(1..))
+                                .default_value("100"),
+                            clap::Arg::new("wishes")
+                                .long("wishes")
+                                .help("Number of wishes of each user. Defaults to 10.")
+                                .value_parser(clap::value_parser!(u32))
+                                .default_value("10"),
+                        ]),
                     ]),
+            clap::Command::new("suite")
+                .about("Run several cases one after another against one playground and server and summarize them")
+                .args(case_args())
+                .args([
+                    clap::Arg::new("cases")
+                        .num_args(1..)
+                        .help("The cases to run as `<case>[:<n>]`, e.g. `one:1000 three:10000`. n defaults to 1000.")
+                        .value_parser(|s: &str| s.parse::<CaseNum>())
+                        .default_values(["one", "two", "three", "four"]),
+                    clap::Arg::new("users")
+                        .short('u')
+                        .long("users")
+                        .help("Number of users of the cases two, four, five and six, instead of their defaults.")
+                        .value_parser(clap::value_parser!(u32).range(1..)),
+                    clap::Arg::new("wipe")
+                        .long("wipe")
+                        .help("Throw away all data before each case, by starting the playground again from scratch.")
+                        .action(clap::ArgAction::SetTrue),
+                ]),
+            clap::Command::new("seed")
+                .about("Insert users and wishes directly into the database of the environment of `testing env up`")
+                .args([
+                    clap::Arg::new("users")
+                        .short('u')
+                        .long("users")
+                        .help("Number of users to insert.")
+                        .value_parser(clap::value_parser!(u32).range(1..))
+                        .default_value("10"),
+                    clap::Arg::new("wishes")
+                        .short('n')
+                        .long("n-wishes")
+                        .help("Number of wishes to insert, shared evenly by the users.")
+                        .value_parser(clap::value_parser!(u64))
+                        .default_value("1000000"),
+                    clap::Arg::new("batch_size")
+                        .long("batch-size")
+                        .help("Records inserted per statement.")
+                        .value_parser(clap::value_parser!(u32).range(1..))
+                        .default_value("10000"),
+                    clap::Arg::new("plain_passwords")
+                        .long("plain-passwords")
+                        .help("Store the passwords as they are instead of hashing them with argon2, if the server compares them in plain text.")
+                        .action(clap::ArgAction::SetTrue),
+                    clap::Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .help("Write the credentials of the users to this file, for `testing case --seeded`.")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .value_hint(ValueHint::FilePath)
+                        .default_value("runs/seed.json"),
+                    seed_arg(),
+                    distribution_arg(),
+                    state_file_arg(),
+                ])
+                .args(content_args())
+                .args(db_args()),
+            clap::Command::new("snapshot")
+                .about("Save the database of the environment of `testing env up` or restore it")
+                .subcommand_required(true)
+                .subcommands([
+                    clap::Command::new("save")
+                        .about("Export all definitions and records of the database to a snapshot"),
+                    clap::Command::new("restore")
+                        .about("Replace the database with a snapshot"),
+                ]
+                .map(|command| {
+                    command
+                        .args([
+                            clap::Arg::new("name")
+                                .required(true)
+                                .help("Name of the snapshot, e.g. `wishes-1m`.")
+                                .value_parser(parse_name),
+                            snapshots_dir_arg(),
+                            state_file_arg(),
+                        ])
+                        .args(db_args())
+                })),
+            clap::Command::new("env")
+                .about("Start a playground and server once and run several cases against it")
+                .subcommand_required(true)
+                .subcommands([
+                    clap::Command::new("up")
+                        .about("Start the environment in the background")
+                        .args([
+                            clap::Arg::new("executable")
+                                .required(true)
+                                .long("surrealdb-executable")
+                                .short('e')
+                                .help("The path to the executable starting the surrealdb server")
+                                .value_parser(clap::value_parser!(PathBuf))
+                                .value_hint(ValueHint::FilePath),
+                            clap::Arg::new("runs_dir")
+                                .long("runs-dir")
+                                .help("The logs of the environment go to a directory below this one.")
+                                .value_parser(clap::value_parser!(PathBuf))
+                                .value_hint(ValueHint::DirPath)
+                                .default_value("runs"),
+                            state_file_arg(),
+                        ])
+                        .args(playground_args()),
+                    clap::Command::new("down")
+                        .about("Stop the environment")
+                        .args([
+                            clap::Arg::new("clean")
+                                .long("clean")
+                                .help("Also remove the data tiup keeps for the playground tag.")
+                                .action(clap::ArgAction::SetTrue),
+                            state_file_arg(),
+                        ]),
+                    clap::Command::new("status")
+                        .about("Show the processes and addresses of the environment")
+                        .arg(state_file_arg()),
+                ]),
+            clap::Command::new("replay")
+                .about("Send the requests recorded with `case --record` again, at the recorded or a scaled speed")
+                .args([
+                    clap::Arg::new("traffic")
+                        .required(true)
+                        .help("The file written by `case --record`.")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .value_hint(ValueHint::FilePath),
+                    clap::Arg::new("url")
+                        .long("url")
+                        .help("Where the server to replay against listens.")
+                        .value_hint(ValueHint::Url)
+                        .default_value("http://localhost:8080"),
+                    clap::Arg::new("speed")
+                        .long("speed")
+                        .help("How much faster than recorded to send the requests, e.g. `2` for twice as fast or `0.5` for half as fast.")
+                        .value_parser(|s: &str| match s.parse::<f64>() {
+                            Ok(speed) if speed > 0.0 && speed.is_finite() => Ok(speed),
+                            _ => Err(format!("invalid speed `{}`, use a positive factor like `2`", s)),
+                        })
+                        .default_value("1"),
+                    clap::Arg::new("request_timeout")
+                        .long("request-timeout")
+                        .help("Seconds after which a request is given up and counted as timeout.")
+                        .value_parser(clap::value_parser!(u64))
+                        .default_value("30"),
+                    clap::Arg::new("runs_dir")
+                        .long("runs-dir")
+                        .help("The results of the replay go to a directory below this one.")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .value_hint(ValueHint::DirPath)
+                        .default_value("runs"),
+                    clap::Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .help("Write the results of the replay as JSON to this file, e.g. for `testing report`.")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .value_hint(ValueHint::FilePath),
+                ]),
+            clap::Command::new("report")
+                .about("Summarize results files written with `case --output`")
+                .args([
+                    clap::Arg::new("results")
+                        .required(true)
+                        .num_args(1..)
+                        .help("Results files to report on. The first one is the baseline for comparisons.")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .value_hint(ValueHint::FilePath),
+                    clap::Arg::new("html")
+                        .long("html")
+                        .help("Render a self-contained HTML report with charts to this file instead of printing a table.")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .value_hint(ValueHint::FilePath),
+                ]),
         ])
 }
+
+/// Options shared by `case` and `suite`.
+fn case_args() -> Vec<clap::Arg> {
+    let mut args = vec![
+        clap::Arg::new("executable")
+            .required_unless_present("env")
+            .long("surrealdb-executable")
+            .short('e')
+            .help("The path to the executable starting the surrealdb server")
+            .value_hint(ValueHint::FilePath),
+        clap::Arg::new("assert")
+            .long("assert")
+            .action(clap::ArgAction::Append)
+            .help("A threshold the run has to satisfy, e.g. `p99<200ms`, `error_rate<0.1%`, `rps>500` or `create_wish:p50<20ms`. Can be given multiple times.")
+            .value_parser(|s: &str| s.parse::<Assertion>()),
+        clap::Arg::new("junit")
+            .long("junit")
+            .help("Write the run and its assertions as JUnit XML to this file.")
+            .value_parser(clap::value_parser!(PathBuf))
+            .value_hint(ValueHint::FilePath),
+        clap::Arg::new("max_error_rate")
+            .long("max-error-rate")
+            .help("Abort the run once more than this share of the measured requests failed, e.g. `5%`.")
+            .value_parser(parse_rate),
+        clap::Arg::new("request_timeout")
+            .long("request-timeout")
+            .help("Seconds after which a request is given up and counted as timeout.")
+            .value_parser(clap::value_parser!(u64))
+            .default_value("30"),
+        clap::Arg::new("metrics_listen")
+            .long("metrics-listen")
+            .help("Serve Prometheus metrics of the running case on this address, e.g. `127.0.0.1:9100`.")
+            .value_parser(clap::value_parser!(SocketAddr)),
+        clap::Arg::new("monitor_interval")
+            .long("monitor-interval")
+            .help("Milliseconds between samples of CPU, memory, threads, open files and I/O of the server, PD and TiKV processes. 0 disables monitoring.")
+            .value_parser(clap::value_parser!(u64))
+            .default_value("1000"),
+        clap::Arg::new("runs_dir")
+            .long("runs-dir")
+            .help("Every run gets its own directory below this one, with the server and tiup logs and the results.")
+            .value_parser(clap::value_parser!(PathBuf))
+            .value_hint(ValueHint::DirPath)
+            .default_value("runs"),
+        clap::Arg::new("env")
+            .long("env")
+            .help("Run against the environment started with `testing env up` instead of starting a playground and server.")
+            .action(clap::ArgAction::SetTrue),
+        clap::Arg::new("keep_data")
+            .long("keep-data")
+            .help("Leave the users and wishes of the run in the database instead of removing them at its end.")
+            .action(clap::ArgAction::SetTrue),
+        clap::Arg::new("seeded")
+            .long("seeded")
+            .requires("env")
+            .help("Read the wishes of the users written by `testing seed` instead of creating some. Only for cases three, four and six.")
+            .value_parser(clap::value_parser!(PathBuf))
+            .value_hint(ValueHint::FilePath),
+        clap::Arg::new("snapshot")
+            .long("snapshot")
+            .requires("env")
+            .help("Restore the database from the snapshot of `testing snapshot save` with this name before the case.")
+            .value_parser(parse_name),
+        snapshots_dir_arg(),
+        seed_arg(),
+        distribution_arg(),
+        clap::Arg::new("jwt_key")
+            .long("jwt-key")
+            .help("HMAC secret the server signs its tokens with, to verify them after every login. Without it, the claims are read without checking the signature.")
+            .value_parser(clap::value_parser!(String)),
+        clap::Arg::new("run_time")
+            .long("run-time")
+            .help("How long the run is planned to take, e.g. `3600s`, to warn about tokens that expire before. Faults given with --chaos count too.")
+            .value_parser(|s: &str| {
+                chaos::parse_duration(s)
+                    .ok_or_else(|| format!("invalid duration `{}`, use e.g. `3600s` or `500ms`", s))
+            }),
+        state_file_arg(),
+    ];
+    args.extend(content_args());
+    args.extend(playground_args());
+    args.extend(db_args());
+    args
+}
+
+/// The options of `case_args`, everything else is off.
+fn case_options(matches: &clap::ArgMatches) -> CaseOptions {
+    CaseOptions {
+        output: None,
+        raw_samples: None,
+        record: None,
+        assertions: matches
+            .get_many::<Assertion>("assert")
+            .map(|assertions| assertions.cloned().collect())
+            .unwrap_or_default(),
+        junit: matches.get_one::<PathBuf>("junit").cloned(),
+        max_error_rate: matches.get_one::<f64>("max_error_rate").copied(),
+        request_timeout: Duration::from_secs(
+            *matches
+                .get_one::<u64>("request_timeout")
+                .expect("request timeout has a default"),
+        ),
+        metrics_listen: matches.get_one::<SocketAddr>("metrics_listen").copied(),
+        monitor_interval: matches
+            .get_one::<u64>("monitor_interval")
+            .filter(|ms| **ms > 0)
+            .map(|ms| Duration::from_millis(*ms)),
+        runs_dir: matches
+            .get_one::<PathBuf>("runs_dir")
+            .cloned()
+            .expect("runs directory has a default"),
+        playground: playground_options(matches),
+        faults: Vec::new(),
+        env: matches.get_flag("env").then(|| state_file(matches)),
+        wipe: false,
+        database: db_options(matches),
+        keep_data: matches.get_flag("keep_data"),
+        seeded: matches.get_one::<PathBuf>("seeded").cloned(),
+        snapshot: matches
+            .get_one::<String>("snapshot")
+            .map(|name| snapshot_path(&snapshots_dir(matches), name)),
+        seed: matches.get_one::<u64>("seed").copied(),
+        content: content_options(matches),
+        distribution: distribution(matches),
+        tokens: TokenOptions {
+            key: matches.get_one::<String>("jwt_key").cloned(),
+            run_time: matches.get_one::<Duration>("run_time").copied(),
+        },
+    }
+}
+
+/// Options of the tiup playground, shared by `case` and `env up`.
+fn playground_args() -> [clap::Arg; 5] {
+    [
+        clap::Arg::new("pd")
+            .long("pd")
+            .help("Number of PD nodes of the playground cluster.")
+            .value_parser(clap::value_parser!(u32).range(1..))
+            .default_value("1"),
+        clap::Arg::new("kv")
+            .long("kv")
+            .help("Number of TiKV nodes of the playground cluster, e.g. 3 for three replicas as in production.")
+            .value_parser(clap::value_parser!(u32).range(1..))
+            .default_value("1"),
+        clap::Arg::new("playground_version")
+            .long("playground-version")
+            .help("Version of PD and TiKV to start, e.g. `v8.5.0`. Defaults to the latest one."),
+        clap::Arg::new("tag")
+            .long("tag")
+            .help("Tag of the playground, which also names its data directory.")
+            .default_value("surrealdb"),
+        clap::Arg::new("tiup_arg")
+            .long("tiup-arg")
+            .action(clap::ArgAction::Append)
+            .allow_hyphen_values(true)
+            .help("An extra flag for `tiup playground`, e.g. `--tiup-arg=--without-monitor`. Can be given multiple times."),
+    ]
+}
+
+fn playground_options(matches: &clap::ArgMatches) -> PlaygroundOptions {
+    PlaygroundOptions {
+        pd: *matches.get_one::<u32>("pd").expect("pd has a default"),
+        kv: *matches.get_one::<u32>("kv").expect("kv has a default"),
+        version: matches.get_one::<String>("playground_version").cloned(),
+        tag: matches
+            .get_one::<String>("tag")
+            .cloned()
+            .expect("tag has a default"),
+        extra_args: matches
+            .get_many::<String>("tiup_arg")
+            .map(|args| args.cloned().collect())
+            .unwrap_or_default(),
+    }
+}
+
+/// Where the server keeps its data, for commands accessing it directly in TiKV.
+fn db_args() -> [clap::Arg; 2] {
+    [
+        clap::Arg::new("db_namespace")
+            .long("db-namespace")
+            .help("SurrealDB namespace the server stores its data in.")
+            .default_value("wishes"),
+        clap::Arg::new("db_database")
+            .long("db-database")
+            .help("SurrealDB database the server stores its data in.")
+            .default_value("wishes"),
+    ]
+}
+
+fn db_options(matches: &clap::ArgMatches) -> DbOptions {
+    DbOptions {
+        namespace: matches
+            .get_one::<String>("db_namespace")
+            .cloned()
+            .expect("namespace has a default"),
+        database: matches
+            .get_one::<String>("db_database")
+            .cloned()
+            .expect("database has a default"),
+    }
+}
+
+/// How generated wishes look, for `case`, `suite` and `seed`.
+fn content_args() -> [clap::Arg; 2] {
+    [
+        clap::Arg::new("wish_length")
+            .long("wish-length")
+            .help("Characters per wish: `fixed:24`, `uniform:10-200`, `normal:80,20` (mean, standard deviation) or `file:<path>` for the lengths of the lines of a file, e.g. real wishes.")
+            .value_parser(|s: &str| s.parse::<Length>())
+            .default_value("fixed:24"),
+        clap::Arg::new("wish_text")
+            .long("wish-text")
+            .help("Characters of the wishes: `ascii` letters and digits, `unicode` with several scripts and emoji, or English `sentences`.")
+            .value_parser(|s: &str| s.parse::<Text>())
+            .default_value("ascii"),
+    ]
+}
+
+fn content_options(matches: &clap::ArgMatches) -> ContentOptions {
+    ContentOptions {
+        length: matches
+            .get_one::<Length>("wish_length")
+            .cloned()
+            .expect("wish length has a default"),
+        text: *matches
+            .get_one::<Text>("wish_text")
+            .expect("wish text has a default"),
+    }
+}
+
+fn seed_arg() -> clap::Arg {
+    clap::Arg::new("seed")
+        .long("seed")
+        .help("Seed of the random users and wishes, to repeat a run with the same data. The seed of every run is printed.")
+        .value_parser(clap::value_parser!(u64))
+}
+
+/// Users of the write cases.
+fn users_arg() -> clap::Arg {
+    clap::Arg::new("users")
+        .short('u')
+        .long("users")
+        .help("Number of users the wishes are shared by. Defaults to 10.")
+        .value_parser(clap::value_parser!(u32).range(1..))
+        .default_value("10")
+}
+
+fn distribution_arg() -> clap::Arg {
+    clap::Arg::new("wish_distribution")
+        .long("wish-distribution")
+        .help("How the wishes are shared by the users: `equal`, `random` for each wish going to a random user, or `power:<exponent>`, e.g. `power:1.5` for a few users with most of the wishes.")
+        .value_parser(|s: &str| s.parse::<Distribution>())
+        .default_value("equal")
+}
+
+fn distribution(matches: &clap::ArgMatches) -> Distribution {
+    *matches
+        .get_one::<Distribution>("wish_distribution")
+        .expect("wish distribution has a default")
+}
+
+fn snapshots_dir_arg() -> clap::Arg {
+    clap::Arg::new("snapshots_dir")
+        .long("snapshots-dir")
+        .help("Directory the snapshots of `testing snapshot` are kept in.")
+        .value_parser(clap::value_parser!(PathBuf))
+        .value_hint(ValueHint::DirPath)
+        .default_value("runs/snapshots")
+}
+
+fn snapshots_dir(matches: &clap::ArgMatches) -> PathBuf {
+    matches
+        .get_one::<PathBuf>("snapshots_dir")
+        .cloned()
+        .expect("snapshots directory has a default")
+}
+
+fn state_file_arg() -> clap::Arg {
+    clap::Arg::new("state_file")
+        .long("state-file")
+        .help("Where `testing env` keeps the state of the running environment.")
+        .value_parser(clap::value_parser!(PathBuf))
+        .value_hint(ValueHint::FilePath)
+        .default_value("runs/env.json")
+}
+
+fn state_file(matches: &clap::ArgMatches) -> PathBuf {
+    matches
+        .get_one::<PathBuf>("state_file")
+        .cloned()
+        .expect("state file has a default")
+}
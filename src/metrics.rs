@@ -0,0 +1,108 @@
+use std::fmt::Write;
+use std::net::SocketAddr;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+use crate::error::Error;
+use crate::results::{Recorder, HISTOGRAM_BOUNDS_MS};
+
+/// Serve the running totals of `recorder` in the Prometheus text format on `/metrics`
+/// until the returned task is aborted.
+pub async fn serve(
+    address: SocketAddr,
+    recorder: Recorder,
+    case: &'static str,
+) -> Result<JoinHandle<()>, Error> {
+    let listener = TcpListener::bind(address).await?;
+    println!("Serving metrics on http://{}/metrics", address);
+    Ok(tokio::spawn(async move {
+        loop {
+            let Ok((mut stream, _)) = listener.accept().await else {
+                continue;
+            };
+            let recorder = recorder.clone();
+            tokio::spawn(async move {
+                // We only need the request line, scrapes have no body.
+                let mut buffer = [0; 1024];
+                let Ok(read) = stream.read(&mut buffer).await else {
+                    return;
+                };
+                let request = String::from_utf8_lossy(&buffer[..read]);
+                let (status, body) = if request.starts_with("GET /metrics") {
+                    ("200 OK", render(&recorder, case))
+                } else {
+                    ("404 Not Found", "not found\n".to_owned())
+                };
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                if let Err(err) = stream.write_all(response.as_bytes()).await {
+                    eprintln!("Failed to answer metrics scrape: {}", err);
+                }
+            });
+        }
+    }))
+}
+
+fn render(recorder: &Recorder, case: &str) -> String {
+    let counters = recorder.live_counters();
+    let mut out = String::new();
+
+    out.push_str("# HELP wishes_case_info The test case that is running.\n");
+    out.push_str("# TYPE wishes_case_info gauge\n");
+    writeln!(out, "wishes_case_info{{case=\"{}\"}} 1", case).unwrap();
+
+    out.push_str("# HELP wishes_requests_total Requests sent to the wishes server by outcome.\n");
+    out.push_str("# TYPE wishes_requests_total counter\n");
+    for (operation, live) in &counters {
+        for (outcome, count) in &live.outcomes {
+            writeln!(
+                out,
+                "wishes_requests_total{{operation=\"{}\",outcome=\"{}\"}} {}",
+                operation, outcome, count
+            )
+            .unwrap();
+        }
+    }
+
+    out.push_str(
+        "# HELP wishes_request_duration_seconds Latency of requests to the wishes server.\n",
+    );
+    out.push_str("# TYPE wishes_request_duration_seconds histogram\n");
+    for (operation, live) in &counters {
+        let mut cumulative = 0;
+        for (bound, count) in HISTOGRAM_BOUNDS_MS.iter().zip(live.buckets) {
+            cumulative += count;
+            let le = if bound.is_finite() {
+                (bound / 1000.0).to_string()
+            } else {
+                "+Inf".to_owned()
+            };
+            writeln!(
+                out,
+                "wishes_request_duration_seconds_bucket{{operation=\"{}\",le=\"{}\"}} {}",
+                operation, le, cumulative
+            )
+            .unwrap();
+        }
+        writeln!(
+            out,
+            "wishes_request_duration_seconds_sum{{operation=\"{}\"}} {}",
+            operation,
+            live.latency_sum.as_secs_f64()
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "wishes_request_duration_seconds_count{{operation=\"{}\"}} {}",
+            operation, cumulative
+        )
+        .unwrap();
+    }
+    out
+}
@@ -0,0 +1,223 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::results::{as_ms, Recorder};
+
+/// Resource usage of one process over the run, as read from `/proc/<pid>`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProcessResources {
+    /// `server`, `pd` or `tikv`.
+    pub name: String,
+    pub pid: u32,
+    pub samples: Vec<ResourceSample>,
+    pub peak: ResourcePeak,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ResourceSample {
+    /// Offset from the start of the run.
+    pub offset_ms: f64,
+    /// CPU usage since the previous sample, 100 is one fully used core.
+    pub cpu_percent: f64,
+    /// User and system CPU time since the process started.
+    pub cpu_seconds: f64,
+    pub rss_bytes: u64,
+    pub threads: u64,
+    pub open_fds: u64,
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ResourcePeak {
+    pub cpu_percent: f64,
+    pub rss_bytes: u64,
+    pub threads: u64,
+    pub open_fds: u64,
+    /// Bytes read and written during the run.
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+}
+
+/// Samples the resources of a set of processes on a background thread until stopped.
+pub struct Monitor {
+    stop: Arc<AtomicBool>,
+    /// Processes to monitor from now on, e.g. a restarted server.
+    follow: Sender<(String, u32)>,
+    pids: Vec<u32>,
+    handle: JoinHandle<Vec<ProcessResources>>,
+}
+
+impl Monitor {
+    /// Monitor the given processes, named e.g. `server`, `pd` or `tikv`.
+    /// Returns `None` where `/proc` is not available.
+    pub fn start(
+        processes: Vec<(String, u32)>,
+        interval: Duration,
+        recorder: Recorder,
+    ) -> Option<Self> {
+        if !Path::new("/proc/self/stat").exists() {
+            println!("Resource monitoring needs /proc and is skipped on this system.");
+            return None;
+        }
+        let names: Vec<String> = processes
+            .iter()
+            .map(|(name, pid)| format!("{} ({})", name, pid))
+            .collect();
+        println!("Monitoring resources of {}.", names.join(", "));
+        let pids = processes.iter().map(|(_, pid)| *pid).collect();
+
+        let ticks = clock_ticks();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::clone(&stop);
+        let (follow, followed) = mpsc::channel::<(String, u32)>();
+        let handle = thread::spawn(move || {
+            let mut resources: Vec<ProcessResources> = processes
+                .into_iter()
+                .map(|(name, pid)| ProcessResources {
+                    name,
+                    pid,
+                    samples: Vec::new(),
+                    peak: ResourcePeak::default(),
+                })
+                .collect();
+            let mut previous: Vec<Option<(Instant, f64)>> = vec![None; resources.len()];
+            loop {
+                for (name, pid) in followed.try_iter() {
+                    resources.push(ProcessResources {
+                        name,
+                        pid,
+                        samples: Vec::new(),
+                        peak: ResourcePeak::default(),
+                    });
+                    previous.push(None);
+                }
+                let now = Instant::now();
+                for (process, previous) in resources.iter_mut().zip(previous.iter_mut()) {
+                    if let Some(sample) = read_sample(process.pid, ticks, now, previous, &recorder)
+                    {
+                        process.samples.push(sample);
+                    }
+                }
+                if stopped.load(Ordering::Relaxed) {
+                    break;
+                }
+                thread::sleep(interval);
+            }
+            for process in &mut resources {
+                process.peak = peak(&process.samples);
+            }
+            resources
+        });
+        Some(Self {
+            stop,
+            follow,
+            pids,
+            handle,
+        })
+    }
+
+    /// Also monitor the processes not monitored yet, e.g. after the server was restarted.
+    /// The ones that exited keep the samples they have.
+    pub fn follow(&mut self, processes: Vec<(String, u32)>) {
+        for (name, pid) in processes {
+            if self.pids.contains(&pid) {
+                continue;
+            }
+            println!("Monitoring resources of {} ({}).", name, pid);
+            self.pids.push(pid);
+            let _ = self.follow.send((name, pid));
+        }
+    }
+
+    /// Takes a last sample and returns everything sampled.
+    pub fn stop(self) -> Vec<ProcessResources> {
+        self.stop.store(true, Ordering::Relaxed);
+        self.handle.join().unwrap_or_else(|_| {
+            eprintln!("Resource monitor panicked!");
+            Vec::new()
+        })
+    }
+}
+
+fn read_sample(
+    pid: u32,
+    ticks: f64,
+    now: Instant,
+    previous: &mut Option<(Instant, f64)>,
+    recorder: &Recorder,
+) -> Option<ResourceSample> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // the command name may contain spaces, the fields we need come after it
+    let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+    let cpu_seconds =
+        (fields.get(11)?.parse::<f64>().ok()? + fields.get(12)?.parse::<f64>().ok()?) / ticks;
+    let threads = fields.get(17)?.parse().ok()?;
+
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid)).unwrap_or_default();
+    let rss_bytes = proc_value(&status, "VmRSS:").unwrap_or_default() * 1024;
+    // reading the io of other users' processes is not permitted, so it stays 0 then
+    let io = std::fs::read_to_string(format!("/proc/{}/io", pid)).unwrap_or_default();
+    let open_fds = std::fs::read_dir(format!("/proc/{}/fd", pid))
+        .map(|entries| entries.count() as u64)
+        .unwrap_or_default();
+
+    let cpu_percent = match previous {
+        Some((at, cpu)) if now > *at => {
+            (cpu_seconds - *cpu) / now.duration_since(*at).as_secs_f64() * 100.0
+        }
+        _ => 0.0,
+    };
+    *previous = Some((now, cpu_seconds));
+
+    Some(ResourceSample {
+        offset_ms: as_ms(recorder.offset(now)),
+        cpu_percent,
+        cpu_seconds,
+        rss_bytes,
+        threads,
+        open_fds,
+        read_bytes: proc_value(&io, "read_bytes:").unwrap_or_default(),
+        write_bytes: proc_value(&io, "write_bytes:").unwrap_or_default(),
+    })
+}
+
+fn peak(samples: &[ResourceSample]) -> ResourcePeak {
+    let mut peak = ResourcePeak::default();
+    for sample in samples {
+        peak.cpu_percent = peak.cpu_percent.max(sample.cpu_percent);
+        peak.rss_bytes = peak.rss_bytes.max(sample.rss_bytes);
+        peak.threads = peak.threads.max(sample.threads);
+        peak.open_fds = peak.open_fds.max(sample.open_fds);
+    }
+    if let (Some(first), Some(last)) = (samples.first(), samples.last()) {
+        peak.read_bytes = last.read_bytes.saturating_sub(first.read_bytes);
+        peak.write_bytes = last.write_bytes.saturating_sub(first.write_bytes);
+    }
+    peak
+}
+
+/// The value of a `key: value` line of a `/proc` file.
+fn proc_value(content: &str, key: &str) -> Option<u64> {
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix(key))
+        .and_then(|rest| rest.split_whitespace().next())
+        .and_then(|value| value.parse().ok())
+}
+
+/// Clock ticks per second, the unit of the CPU times in `/proc/<pid>/stat`.
+fn clock_ticks() -> f64 {
+    Command::new("getconf")
+        .arg("CLK_TCK")
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8_lossy(&output.stdout).trim().parse().ok())
+        .unwrap_or(100.0)
+}
@@ -0,0 +1,572 @@
+use clap_complete::Shell;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::process::{Child, Command};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::error::Error;
+use crate::run_dir::RunDir;
+
+pub const TIUP_LOG: &str = "tiup.log";
+/// How often the log is checked for new output while tiup starts the cluster.
+const STARTUP_POLL: Duration = Duration::from_millis(100);
+/// How long tiup may take to start the cluster, including downloading the components.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(600);
+/// Process names of the playground components and the role they play.
+const COMPONENTS: [(&str, &str); 4] = [
+    ("pd-server", "pd"),
+    ("tikv-server", "tikv"),
+    ("prometheus", "prometheus"),
+    ("grafana-server", "grafana"),
+];
+
+/// A running `tiup playground` cluster.
+pub struct Playground {
+    tiup: Child,
+    tag: String,
+    topology: Topology,
+    /// Components we started again ourselves after killing them, tiup does not know them.
+    restarted: Vec<Child>,
+}
+
+/// How the playground cluster is set up.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlaygroundOptions {
+    /// Number of PD nodes.
+    pub pd: u32,
+    /// Number of TiKV nodes.
+    pub kv: u32,
+    /// Version of the components, e.g. `v8.5.0`, `None` for the latest one.
+    pub version: Option<String>,
+    /// Tag of the playground, which also names its data directory.
+    pub tag: String,
+    /// Further flags for `tiup playground`, e.g. `--without-monitor`.
+    pub extra_args: Vec<String>,
+}
+
+/// Where the components of the playground listen and which processes they run in.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Topology {
+    /// `host:port` of every PD, the first one is what the server connects to.
+    pub pd: Vec<String>,
+    /// `host:port` of every TiKV.
+    pub tikv: Vec<String>,
+    pub prometheus: Option<String>,
+    pub grafana: Option<String>,
+    /// The processes tiup started for the cluster.
+    pub components: Vec<Component>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Component {
+    /// `pd`, `tikv`, `prometheus` or `grafana`.
+    pub role: String,
+    pub pid: u32,
+    /// The address from the command line of the process, if it has one.
+    pub address: Option<String>,
+    /// Program and arguments, to start the component again after it was killed.
+    #[serde(default)]
+    pub command: Vec<String>,
+}
+
+impl Playground {
+    /// Start the playground and wait until it reports the cluster as started.
+    pub fn start(
+        shell: &Shell,
+        options: &PlaygroundOptions,
+        run_dir: &RunDir,
+    ) -> Result<Self, Error> {
+        let tiup_log = run_dir.log(TIUP_LOG)?;
+        println!(
+            "Starting tiup playground, see {} for its output.",
+            run_dir.file(TIUP_LOG).display()
+        );
+        // tiup writes to the log file, not to a pipe, so it can outlive us with `env up`
+        let mut reader = BufReader::new(File::open(run_dir.file(TIUP_LOG))?);
+        reader.seek(SeekFrom::End(0))?;
+        let tiup = Command::new(shell.to_string())
+            .arg("-c")
+            .arg(options.command())
+            .stdout(tiup_log.try_clone()?)
+            .stderr(tiup_log)
+            .spawn()
+            .map_err(|err| {
+                Error::Invalid(format!(
+                    "cannot start tiup playground with {}: {}",
+                    shell, err
+                ))
+            })?;
+        let mut playground = Self {
+            tiup,
+            tag: options.tag.clone(),
+            topology: Topology::default(),
+            restarted: Vec::new(),
+        };
+        let monitored = options.monitored();
+        let started = Instant::now();
+        let mut line = String::new();
+        loop {
+            if reader.read_line(&mut line)? > 0 && line.ends_with('\n') {
+                match playground.topology.read_line(&line, monitored) {
+                    Ok(true) => break,
+                    Ok(false) => {}
+                    Err(err) => {
+                        playground.stop();
+                        return Err(err);
+                    }
+                }
+                line.clear();
+                continue;
+            }
+            if playground.tiup.try_wait()?.is_some() {
+                return Err(Error::Invalid(format!(
+                    "tiup playground stopped before reporting the cluster as started, see {}",
+                    run_dir.file(TIUP_LOG).display()
+                )));
+            }
+            if started.elapsed() > STARTUP_TIMEOUT {
+                playground.stop();
+                return Err(Error::Invalid(format!(
+                    "tiup playground did not report the cluster as started within {} s, see {}",
+                    STARTUP_TIMEOUT.as_secs(),
+                    run_dir.file(TIUP_LOG).display()
+                )));
+            }
+            thread::sleep(STARTUP_POLL);
+        }
+        if playground.topology.pd.is_empty() {
+            playground.stop();
+            return Err(Error::Invalid(format!(
+                "tiup playground reported no PD endpoints, see {}",
+                run_dir.file(TIUP_LOG).display()
+            )));
+        }
+        let pid = playground.tiup.id();
+        playground.topology.find_components(pid);
+        println!("Playground started: {}.", playground.topology);
+        if playground.topology.pd.len() != options.pd as usize {
+            eprintln!(
+                "Asked for {} PD nodes, but the playground reports {}.",
+                options.pd,
+                playground.topology.pd.len()
+            );
+        }
+        Ok(playground)
+    }
+
+    pub fn topology(&self) -> &Topology {
+        &self.topology
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.tiup.id()
+    }
+
+    /// Stop the cluster and wait for tiup to exit.
+    pub fn stop(&mut self) {
+        println!("Stopping tiup cluster!");
+        if !interrupt(&self.tag) {
+            // not registered with tiup (yet), so stop it directly
+            let _ = self.tiup.kill();
+        }
+        self.tiup.wait().expect("failed to wait for tiup to stop");
+        for mut component in self.restarted.drain(..) {
+            let _ = component.kill();
+            let _ = component.wait();
+        }
+    }
+
+    /// Take care of a component that was started again outside of tiup, in place of the
+    /// process `replaced`.
+    pub fn adopt(&mut self, replaced: u32, component: Child) {
+        self.topology.replace_pid(replaced, component.id());
+        self.restarted.push(component);
+    }
+}
+
+impl PlaygroundOptions {
+    /// The shell command starting the playground.
+    fn command(&self) -> String {
+        let mut command = "exec tiup playground".to_owned();
+        if let Some(version) = &self.version {
+            command += &format!(" {}", quote(version));
+        }
+        command += &format!(
+            " --tag {} --mode tikv-slim --pd {} --kv {}",
+            quote(&self.tag),
+            self.pd,
+            self.kv
+        );
+        for arg in &self.extra_args {
+            command += &format!(" {}", quote(arg));
+        }
+        command
+    }
+
+    /// Whether the playground starts Prometheus and Grafana.
+    fn monitored(&self) -> bool {
+        !self.extra_args.iter().any(|arg| arg == "--without-monitor")
+    }
+}
+
+impl Topology {
+    /// Take what we need from one line of the startup output of tiup playground.
+    /// Returns `true` on the last line tiup prints once the cluster is started,
+    /// which is the Grafana address or, without monitoring, the PD endpoints.
+    fn read_line(&mut self, line: &str, monitored: bool) -> Result<bool, Error> {
+        let line = line.trim();
+        if let Some((_, endpoints)) = line.split_once("PD Endpoints:") {
+            for endpoint in endpoints.split([' ', ',']).filter(|e| !e.is_empty()) {
+                self.pd.push(parse_address(endpoint, line)?);
+            }
+            return Ok(!monitored);
+        } else if let Some(rest) = line.strip_prefix("Waiting for tikv ") {
+            // e.g. `Waiting for tikv 127.0.0.1:20160 ready`
+            let address = rest.split_whitespace().next().unwrap_or_default();
+            self.tikv.push(parse_address(address, line)?);
+        } else if let Some((_, url)) = line.split_once("Prometheus:") {
+            self.prometheus = Some(parse_url(url, line)?);
+        } else if let Some((_, url)) = line.split_once("Grafana:") {
+            self.grafana = Some(parse_url(url, line)?);
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// Point the component running as `old` at its new process.
+    pub fn replace_pid(&mut self, old: u32, new: u32) {
+        for component in &mut self.components {
+            if component.pid == old {
+                component.pid = new;
+            }
+        }
+    }
+
+    /// Look up the component processes below the tiup process `tiup_pid`.
+    fn find_components(&mut self, tiup_pid: u32) {
+        for (pid, command) in descendants(tiup_pid) {
+            // arguments may contain spaces, which the output of ps does not tell apart
+            let argv = std::fs::read(format!("/proc/{}/cmdline", pid))
+                .map(|raw| split_cmdline(&raw))
+                .unwrap_or_default();
+            let words: Vec<&str> = if argv.is_empty() {
+                command.split_whitespace().collect()
+            } else {
+                argv.iter().map(String::as_str).collect()
+            };
+            let Some((program, args)) = words.split_first() else {
+                continue;
+            };
+            let program = program.rsplit('/').next().unwrap_or(program);
+            let Some((_, role)) = COMPONENTS.iter().find(|(name, _)| *name == program) else {
+                continue;
+            };
+            let flag = match *role {
+                "pd" => "--client-urls",
+                "tikv" => "--addr",
+                _ => "--web.listen-address",
+            };
+            let address = args
+                .iter()
+                .enumerate()
+                .find_map(|(index, arg)| match arg.strip_prefix(flag) {
+                    Some("") => args.get(index + 1).copied(),
+                    Some(value) => value.strip_prefix('='),
+                    None => None,
+                })
+                .map(|address| address.trim_start_matches("http://").to_owned());
+            self.components.push(Component {
+                role: role.to_string(),
+                pid,
+                address,
+                // without /proc the arguments are unknown, so kill faults are refused
+                command: argv.clone(),
+            });
+        }
+        if self.tikv.is_empty() {
+            self.tikv = self
+                .components
+                .iter()
+                .filter(|component| component.role == "tikv")
+                .filter_map(|component| component.address.clone())
+                .collect();
+        }
+        if !self.components.iter().any(|c| c.role == "pd") {
+            eprintln!("Found no PD process of the playground, it is not monitored.");
+        }
+    }
+
+    /// The PD the server connects to.
+    pub fn pd_endpoint(&self) -> &str {
+        &self.pd[0]
+    }
+
+    /// Role and pid of the PD and TiKV processes.
+    pub fn storage_processes(&self) -> Vec<(String, u32)> {
+        self.components
+            .iter()
+            .filter(|component| component.role == "pd" || component.role == "tikv")
+            .map(|component| (component.role.clone(), component.pid))
+            .collect()
+    }
+}
+
+impl fmt::Display for Topology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // e.g. `127.0.0.1:2379 (pid 4242)`
+        let nodes = |addresses: &[String], role: &str| {
+            let pids: Vec<String> = self
+                .components
+                .iter()
+                .filter(|component| component.role == role)
+                .map(|component| component.pid.to_string())
+                .collect();
+            let mut text = addresses.join(", ");
+            if !pids.is_empty() {
+                if !text.is_empty() {
+                    text.push(' ');
+                }
+                text += &format!("(pid {})", pids.join(", "));
+            }
+            text
+        };
+        write!(f, "PD {}", nodes(&self.pd, "pd"))?;
+        let tikv = nodes(&self.tikv, "tikv");
+        if !tikv.is_empty() {
+            write!(f, ", TiKV {}", tikv)?;
+        }
+        if let Some(prometheus) = &self.prometheus {
+            write!(f, ", Prometheus {}", prometheus)?;
+        }
+        if let Some(grafana) = &self.grafana {
+            write!(f, ", Grafana {}", grafana)?;
+        }
+        Ok(())
+    }
+}
+
+/// Check that `address` is a `host:port` pair.
+fn parse_address(address: &str, line: &str) -> Result<String, Error> {
+    match address.rsplit_once(':') {
+        Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => {
+            Ok(address.to_owned())
+        }
+        _ => Err(Error::Invalid(format!(
+            "unexpected output of tiup playground, `{}` is no host:port address: {}",
+            address, line
+        ))),
+    }
+}
+
+fn parse_url(url: &str, line: &str) -> Result<String, Error> {
+    let url = url.trim();
+    match url.strip_prefix("http://") {
+        Some(address) => parse_address(address.trim_end_matches('/'), line).map(|_| url.to_owned()),
+        None => Err(Error::Invalid(format!(
+            "unexpected output of tiup playground, `{}` is no http url: {}",
+            url, line
+        ))),
+    }
+}
+
+/// Pid and command line of all processes below `root` in the process tree.
+fn descendants(root: u32) -> Vec<(u32, String)> {
+    let Ok(output) = Command::new("ps")
+        .args(["-A", "-o", "pid=,ppid=,args="])
+        .output()
+    else {
+        eprintln!("Failed to list processes, the playground components are unknown.");
+        return Vec::new();
+    };
+    let processes: Vec<(u32, u32, String)> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let pid = fields.next()?.parse().ok()?;
+            let ppid = fields.next()?.parse().ok()?;
+            Some((pid, ppid, fields.collect::<Vec<_>>().join(" ")))
+        })
+        .collect();
+    let mut parents = vec![root];
+    let mut found = Vec::new();
+    let mut index = 0;
+    while index < parents.len() {
+        let parent = parents[index];
+        for (pid, _, args) in processes.iter().filter(|(_, ppid, _)| *ppid == parent) {
+            parents.push(*pid);
+            found.push((*pid, args.clone()));
+        }
+        index += 1;
+    }
+    found
+}
+
+/// The arguments of a `/proc/<pid>/cmdline` file, each one ends with a NUL byte.
+fn split_cmdline(raw: &[u8]) -> Vec<String> {
+    let raw = raw.strip_suffix(&[0]).unwrap_or(raw);
+    if raw.is_empty() {
+        return Vec::new();
+    }
+    raw.split(|&byte| byte == 0)
+        .map(|arg| String::from_utf8_lossy(arg).into_owned())
+        .collect()
+}
+
+/// Quote `arg` for the shell.
+fn quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+/// Send SIGINT to the playground tagged `tag`, which stops the cluster.
+/// Returns `false` if tiup does not know such a playground.
+pub fn interrupt(tag: &str) -> bool {
+    let Some(pid) = playground_pid(tag) else {
+        return false;
+    };
+    Command::new("kill")
+        .arg("-2")
+        .arg(pid.to_string())
+        .status()
+        .expect("failed to send SIGINT to tiup");
+    true
+}
+
+fn playground_pid(tag: &str) -> Option<u32> {
+    // Run the `tiup status` command
+    let output = Command::new("tiup")
+        .arg("status")
+        .output()
+        .expect("failed to execute tiup status");
+
+    // Convert the output to a string
+    let output_str = String::from_utf8_lossy(&output.stdout);
+
+    // Iterate through the lines to find the component
+    for line in output_str.lines().skip(1) {
+        // Skip the header line
+        let columns: Vec<&str> = line.split_whitespace().collect();
+        if columns.len() >= 3 && columns[0] == tag {
+            // Parse the PID
+            if let Ok(pid) = columns[2].parse::<u32>() {
+                return Some(pid);
+            }
+        }
+    }
+
+    // If the component is not found
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The startup output of `tiup playground --mode tikv-slim`.
+    const OUTPUT: &str = "\
+Start pd instance: v8.5.0
+Start tikv instance: v8.5.0
+Waiting for tikv 127.0.0.1:20160 ready
+Waiting for tikv 127.0.0.1:20161 ready
+PD Endpoints:   127.0.0.1:2379 127.0.0.1:2382
+To view the Prometheus: http://127.0.0.1:9090
+To view the Grafana: http://127.0.0.1:3000
+";
+
+    fn read(output: &str, monitored: bool) -> (Topology, Option<usize>) {
+        let mut topology = Topology::default();
+        let started = output.lines().position(|line| {
+            topology
+                .read_line(line, monitored)
+                .expect("the line is valid")
+        });
+        (topology, started)
+    }
+
+    #[test]
+    fn reads_the_startup_output() {
+        let (topology, started) = read(OUTPUT, true);
+        assert_eq!(started, Some(6));
+        assert_eq!(topology.pd, ["127.0.0.1:2379", "127.0.0.1:2382"]);
+        assert_eq!(topology.tikv, ["127.0.0.1:20160", "127.0.0.1:20161"]);
+        assert_eq!(
+            topology.prometheus.as_deref(),
+            Some("http://127.0.0.1:9090")
+        );
+        assert_eq!(topology.grafana.as_deref(), Some("http://127.0.0.1:3000"));
+        assert_eq!(topology.pd_endpoint(), "127.0.0.1:2379");
+    }
+
+    #[test]
+    fn is_started_at_the_pd_endpoints_without_monitoring() {
+        let (topology, started) = read(OUTPUT, false);
+        assert_eq!(started, Some(4));
+        assert_eq!(topology.pd.len(), 2);
+        assert_eq!(topology.prometheus, None);
+    }
+
+    #[test]
+    fn reads_comma_separated_pd_endpoints() {
+        let mut topology = Topology::default();
+        let started = topology
+            .read_line("PD Endpoints:   127.0.0.1:2379,127.0.0.1:2382\n", false)
+            .unwrap();
+        assert!(started);
+        assert_eq!(topology.pd, ["127.0.0.1:2379", "127.0.0.1:2382"]);
+    }
+
+    #[test]
+    fn rejects_invalid_endpoints() {
+        let mut topology = Topology::default();
+        assert!(topology
+            .read_line("PD Endpoints:   127.0.0.1", false)
+            .is_err());
+        assert!(topology
+            .read_line("Waiting for tikv localhost:port ready", false)
+            .is_err());
+        assert!(topology
+            .read_line("To view the Grafana: 127.0.0.1:3000", true)
+            .is_err());
+    }
+
+    #[test]
+    fn parses_addresses() {
+        assert_eq!(
+            parse_address("127.0.0.1:2379", "").unwrap(),
+            "127.0.0.1:2379"
+        );
+        assert_eq!(parse_address("[::1]:2379", "").unwrap(), "[::1]:2379");
+        assert!(parse_address("127.0.0.1", "").is_err());
+        assert!(parse_address(":2379", "").is_err());
+        assert!(parse_address("127.0.0.1:99999", "").is_err());
+    }
+
+    #[test]
+    fn parses_urls() {
+        assert_eq!(
+            parse_url(" http://127.0.0.1:3000/ ", "").unwrap(),
+            "http://127.0.0.1:3000/"
+        );
+        assert!(parse_url("https://127.0.0.1:3000", "").is_err());
+        assert!(parse_url("http://127.0.0.1", "").is_err());
+    }
+
+    #[test]
+    fn keeps_spaces_in_command_line_arguments() {
+        let raw = b"/usr/bin/tikv-server\0--addr\x00127.0.0.1:20160\0--data-dir\0/tmp/my data\0";
+        assert_eq!(
+            split_cmdline(raw),
+            [
+                "/usr/bin/tikv-server",
+                "--addr",
+                "127.0.0.1:20160",
+                "--data-dir",
+                "/tmp/my data"
+            ]
+        );
+        assert_eq!(split_cmdline(b"pd-server\0\0"), ["pd-server", ""]);
+        assert!(split_cmdline(b"").is_empty());
+    }
+}
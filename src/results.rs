@@ -0,0 +1,718 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::chaos::FaultSummary;
+use crate::error::Error;
+use crate::monitor::ProcessResources;
+use crate::playground::Topology;
+use crate::traffic::TrafficLog;
+
+/// Upper bounds (in ms) of the latency histogram buckets. The last bucket is unbounded.
+pub const HISTOGRAM_BOUNDS_MS: [f64; 18] = [
+    0.5,
+    1.0,
+    2.0,
+    5.0,
+    10.0,
+    20.0,
+    50.0,
+    100.0,
+    200.0,
+    500.0,
+    1000.0,
+    2000.0,
+    5000.0,
+    10000.0,
+    20000.0,
+    50000.0,
+    100000.0,
+    f64::INFINITY,
+];
+
+/// Requests to wait for before the error rate may abort a run, so single early errors do not.
+const MIN_REQUESTS_FOR_ABORT: u64 = 20;
+
+/// The number of points the throughput timeline is aimed at.
+const TIMELINE_POINTS: u128 = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Operation {
+    Register,
+    Login,
+    CreateWish,
+    GetWishes,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Operation::Register => "register",
+            Operation::Login => "login",
+            Operation::CreateWish => "create_wish",
+            Operation::GetWishes => "get_wishes",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Why a request did not succeed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ErrorKind {
+    /// The connection to the server could not be established.
+    Connect,
+    Timeout,
+    /// The server answered with a non-2xx status code.
+    Status(u16),
+    /// The response body could not be read or is not what we expected.
+    Decode,
+    Other,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::Connect => write!(f, "connect"),
+            ErrorKind::Timeout => write!(f, "timeout"),
+            ErrorKind::Status(code) => write!(f, "http_{}", code),
+            ErrorKind::Decode => write!(f, "decode"),
+            ErrorKind::Other => write!(f, "other"),
+        }
+    }
+}
+
+/// A classified request failure.
+#[derive(Clone, Debug)]
+pub struct Failure {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.message, self.kind)
+    }
+}
+
+impl From<reqwest::Error> for Failure {
+    fn from(err: reqwest::Error) -> Self {
+        let kind = if err.is_timeout() {
+            ErrorKind::Timeout
+        } else if err.is_connect() {
+            ErrorKind::Connect
+        } else if err.is_decode() || err.is_body() {
+            ErrorKind::Decode
+        } else if let Some(status) = err.status() {
+            ErrorKind::Status(status.as_u16())
+        } else {
+            ErrorKind::Other
+        };
+        Self {
+            kind,
+            message: err.to_string(),
+        }
+    }
+}
+
+/// One request sent to the server.
+#[derive(Clone, Debug)]
+pub struct Sample {
+    pub operation: Operation,
+    /// Offset of the request start from the start of the run.
+    pub start: Duration,
+    pub latency: Duration,
+    pub status: Option<u16>,
+    pub bytes: u64,
+    pub error: Option<ErrorKind>,
+    pub message: Option<String>,
+}
+
+impl Sample {
+    pub fn is_error(&self) -> bool {
+        self.error.is_some()
+    }
+}
+
+/// Collects the samples of all requests of a run. Cheap to clone, all clones share the samples.
+#[derive(Clone)]
+pub struct Recorder {
+    origin: Instant,
+    state: Arc<Mutex<RecorderState>>,
+    /// Where every request and response is written to, if the run is recorded.
+    traffic: Option<Arc<TrafficLog>>,
+}
+
+struct RecorderState {
+    samples: Vec<Sample>,
+    measure_from: Duration,
+    measure_until: Option<Duration>,
+    /// Whether `start_measuring` was called, setup requests do not count towards an abort.
+    measuring: bool,
+    /// Requests and failed requests started in the measured phase.
+    measured: u64,
+    measured_errors: u64,
+    max_error_rate: Option<f64>,
+    aborted: bool,
+    live: BTreeMap<Operation, LiveCounters>,
+}
+
+/// Running totals of one operation, kept up to date during the run.
+#[derive(Clone, Debug)]
+pub struct LiveCounters {
+    /// Requests per outcome, `ok` or an [`ErrorKind`].
+    pub outcomes: BTreeMap<String, u64>,
+    /// Requests per bucket of [`HISTOGRAM_BOUNDS_MS`], not cumulative.
+    pub buckets: [u64; HISTOGRAM_BOUNDS_MS.len()],
+    pub latency_sum: Duration,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+            state: Arc::new(Mutex::new(RecorderState {
+                samples: Vec::new(),
+                measure_from: Duration::ZERO,
+                measure_until: None,
+                measuring: false,
+                measured: 0,
+                measured_errors: 0,
+                max_error_rate: None,
+                aborted: false,
+                live: BTreeMap::new(),
+            })),
+            traffic: None,
+        }
+    }
+
+    /// Write every request and response to `traffic`.
+    pub fn with_traffic(mut self, traffic: Option<TrafficLog>) -> Self {
+        self.traffic = traffic.map(Arc::new);
+        self
+    }
+
+    pub fn traffic(&self) -> Option<&TrafficLog> {
+        self.traffic.as_deref()
+    }
+
+    /// Abort the run once more than this fraction of the measured requests failed.
+    pub fn with_max_error_rate(self, max_error_rate: Option<f64>) -> Self {
+        self.state
+            .lock()
+            .expect("failed to lock recorder")
+            .max_error_rate = max_error_rate;
+        self
+    }
+
+    /// Whether the error rate crossed the maximum, request helpers stop sending then.
+    pub fn aborted(&self) -> bool {
+        self.state.lock().expect("failed to lock recorder").aborted
+    }
+
+    /// The error rate of the measured requests so far, if the run has been aborted because of it.
+    pub fn abort_reason(&self) -> Option<String> {
+        let state = self.state.lock().expect("failed to lock recorder");
+        state.aborted.then(|| {
+            format!(
+                "aborted after {} of {} measured requests failed, more than the allowed {}%",
+                state.measured_errors,
+                state.measured,
+                state.max_error_rate.unwrap_or_default() * 100.0
+            )
+        })
+    }
+
+    /// Offset of `instant` from the start of the run.
+    pub fn offset(&self, instant: Instant) -> Duration {
+        instant.saturating_duration_since(self.origin)
+    }
+
+    pub fn record(&self, sample: Sample) {
+        let mut state = self.state.lock().expect("failed to lock recorder");
+        if state.measuring && sample.start >= state.measure_from {
+            state.measured += 1;
+            if sample.is_error() {
+                state.measured_errors += 1;
+            }
+        }
+        let live = state
+            .live
+            .entry(sample.operation)
+            .or_insert_with(|| LiveCounters {
+                outcomes: BTreeMap::new(),
+                buckets: [0; HISTOGRAM_BOUNDS_MS.len()],
+                latency_sum: Duration::ZERO,
+            });
+        let outcome = sample
+            .error
+            .map(|kind| kind.to_string())
+            .unwrap_or_else(|| "ok".to_owned());
+        *live.outcomes.entry(outcome).or_insert(0) += 1;
+        live.buckets[bucket_index(sample.latency)] += 1;
+        live.latency_sum += sample.latency;
+        state.samples.push(sample);
+        if let Some(max_error_rate) = state.max_error_rate {
+            let requests = state.measured;
+            if requests >= MIN_REQUESTS_FOR_ABORT
+                && state.measured_errors as f64 / requests as f64 > max_error_rate
+            {
+                state.aborted = true;
+            }
+        }
+    }
+
+    /// A snapshot of the running totals per operation, setup requests included.
+    pub fn live_counters(&self) -> BTreeMap<Operation, LiveCounters> {
+        self.state
+            .lock()
+            .expect("failed to lock recorder")
+            .live
+            .clone()
+    }
+
+    /// Everything recorded before this call is treated as setup and not measured.
+    pub fn start_measuring(&self) {
+        let now = self.offset(Instant::now());
+        let mut state = self.state.lock().expect("failed to lock recorder");
+        state.measure_from = now;
+        state.measuring = true;
+        // everything recorded so far started before now
+        state.measured = 0;
+        state.measured_errors = 0;
+    }
+
+    /// Ends the measured phase, so teardown does not count into the elapsed time.
+    pub fn stop_measuring(&self) {
+        let now = self.offset(Instant::now());
+        self.state
+            .lock()
+            .expect("failed to lock recorder")
+            .measure_until = Some(now);
+    }
+
+    pub fn measure_from(&self) -> Duration {
+        self.state
+            .lock()
+            .expect("failed to lock recorder")
+            .measure_from
+    }
+
+    /// Duration of the measured phase, up to now if it has not been stopped yet.
+    pub fn elapsed(&self) -> Duration {
+        let state = self.state.lock().expect("failed to lock recorder");
+        state
+            .measure_until
+            .unwrap_or_else(|| self.offset(Instant::now()))
+            .saturating_sub(state.measure_from)
+    }
+
+    /// Write every recorded request as one CSV row, setup requests included.
+    pub fn write_csv(&self, path: &Path) -> Result<(), Error> {
+        let state = self.state.lock().expect("failed to lock recorder");
+        let mut out = std::io::BufWriter::new(std::fs::File::create(path)?);
+        writeln!(
+            out,
+            "operation,start_ms,latency_ms,status,bytes,error_kind,error,measured"
+        )?;
+        for sample in &state.samples {
+            writeln!(
+                out,
+                "{},{:.3},{:.3},{},{},{},{},{}",
+                sample.operation,
+                as_ms(sample.start),
+                as_ms(sample.latency),
+                sample.status.map(|s| s.to_string()).unwrap_or_default(),
+                sample.bytes,
+                sample.error.map(|e| e.to_string()).unwrap_or_default(),
+                sample.message.as_deref().map(csv_field).unwrap_or_default(),
+                sample.start >= state.measure_from
+            )?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    /// All samples of requests started in the measured phase.
+    pub fn measured_samples(&self) -> Vec<Sample> {
+        let state = self.state.lock().expect("failed to lock recorder");
+        state
+            .samples
+            .iter()
+            .filter(|sample| sample.start >= state.measure_from)
+            .cloned()
+            .collect()
+    }
+}
+
+/// The aggregated outcome of one test case run, as written with `case --output`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RunResults {
+    pub case: String,
+    pub description: String,
+    /// Unix timestamp (seconds) of the start of the run.
+    pub started_at: u64,
+    /// Duration of the measured phase.
+    pub elapsed_ms: f64,
+    pub overall: Overall,
+    pub operations: Vec<OperationSummary>,
+    /// Resource usage of the server and the TiKV cluster during the run.
+    #[serde(default)]
+    pub resources: Vec<ProcessResources>,
+    /// The playground the run was measured against.
+    #[serde(default)]
+    pub topology: Option<Topology>,
+    /// Faults injected during the run and the requests around them.
+    #[serde(default)]
+    pub faults: Vec<FaultSummary>,
+    /// What survived a restart, for the durability case.
+    #[serde(default)]
+    pub durability: Option<Durability>,
+    /// Seed of the generated users and wishes, to repeat the run with `--seed`.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// How the content of the created wishes was generated, e.g. `ascii text of fixed:24 characters`.
+    #[serde(default)]
+    pub wish_content: Option<String>,
+    /// Wishes the case asked the server to create and how many it did.
+    #[serde(default)]
+    pub created_wishes: Option<CreatedWishes>,
+}
+
+/// Wishes created by a case.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CreatedWishes {
+    pub requested: u64,
+    pub created: u64,
+    /// How they were shared by the users, e.g. `power:1.5`.
+    pub distribution: String,
+}
+
+/// Users and wishes checked after restarting the server.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Durability {
+    pub users: u64,
+    /// Users who cannot log in any more.
+    pub users_lost: u64,
+    pub wishes: u64,
+    pub wishes_missing: u64,
+    /// Wishes whose content or status differ from before the restart.
+    pub wishes_changed: u64,
+}
+
+/// All measured requests together, regardless of their operation.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Overall {
+    pub count: u64,
+    pub errors: u64,
+    pub errors_by_kind: BTreeMap<String, u64>,
+    pub throughput: f64,
+    pub latency: LatencySummary,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OperationSummary {
+    pub operation: Operation,
+    pub count: u64,
+    pub errors: u64,
+    /// Failed requests per [`ErrorKind`], e.g. `timeout` or `http_500`.
+    pub errors_by_kind: BTreeMap<String, u64>,
+    /// Response bytes received.
+    pub bytes: u64,
+    /// Requests per second over the measured phase.
+    pub throughput: f64,
+    pub latency: LatencySummary,
+    pub histogram: Vec<HistogramBucket>,
+    pub timeline: Timeline,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LatencySummary {
+    pub min_ms: f64,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HistogramBucket {
+    /// Inclusive upper bound of the bucket, `None` for the last, unbounded one.
+    pub upper_ms: Option<f64>,
+    pub count: u64,
+}
+
+/// Completed requests per interval, counted from the start of the measured phase.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Timeline {
+    pub interval_ms: u64,
+    pub requests: Vec<u64>,
+    pub errors: Vec<u64>,
+}
+
+impl RunResults {
+    pub fn new(case: &str, description: &str, started_at: SystemTime, recorder: &Recorder) -> Self {
+        let elapsed = recorder.elapsed();
+        let measure_from = recorder.measure_from();
+        let samples = recorder.measured_samples();
+
+        let mut operations: Vec<Operation> = samples.iter().map(|s| s.operation).collect();
+        operations.sort();
+        operations.dedup();
+
+        let operations = operations
+            .into_iter()
+            .map(|operation| {
+                let samples: Vec<&Sample> = samples
+                    .iter()
+                    .filter(|sample| sample.operation == operation)
+                    .collect();
+                OperationSummary::new(operation, &samples, measure_from, elapsed)
+            })
+            .collect();
+
+        Self {
+            case: case.to_owned(),
+            description: description.to_owned(),
+            started_at: started_at
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            elapsed_ms: as_ms(elapsed),
+            overall: Overall::new(&samples.iter().collect::<Vec<_>>(), elapsed),
+            operations,
+            resources: Vec::new(),
+            topology: None,
+            faults: Vec::new(),
+            durability: None,
+            seed: None,
+            wish_content: None,
+            created_wishes: None,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let file = std::fs::File::open(path)?;
+        Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let file = std::fs::File::create(path)?;
+        serde_json::to_writer_pretty(std::io::BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    pub fn total_requests(&self) -> u64 {
+        self.overall.count
+    }
+
+    pub fn total_errors(&self) -> u64 {
+        self.overall.errors
+    }
+}
+
+impl OperationSummary {
+    fn new(
+        operation: Operation,
+        samples: &[&Sample],
+        measure_from: Duration,
+        elapsed: Duration,
+    ) -> Self {
+        let mut histogram: Vec<HistogramBucket> = HISTOGRAM_BOUNDS_MS
+            .iter()
+            .map(|bound| HistogramBucket {
+                upper_ms: bound.is_finite().then_some(*bound),
+                count: 0,
+            })
+            .collect();
+        for sample in samples {
+            histogram[bucket_index(sample.latency)].count += 1;
+        }
+
+        let interval_ms = (elapsed.as_millis() / TIMELINE_POINTS).max(100) as u64;
+        let points = (elapsed.as_millis() as u64 / interval_ms + 1) as usize;
+        let mut timeline = Timeline {
+            interval_ms,
+            requests: vec![0; points],
+            errors: vec![0; points],
+        };
+        for sample in samples {
+            let done = (sample.start + sample.latency).saturating_sub(measure_from);
+            let point = ((done.as_millis() as u64 / interval_ms) as usize).min(points - 1);
+            timeline.requests[point] += 1;
+            if sample.is_error() {
+                timeline.errors[point] += 1;
+            }
+        }
+
+        let overall = Overall::new(samples, elapsed);
+        Self {
+            operation,
+            count: overall.count,
+            errors: overall.errors,
+            errors_by_kind: overall.errors_by_kind,
+            bytes: samples.iter().map(|s| s.bytes).sum(),
+            throughput: overall.throughput,
+            latency: overall.latency,
+            histogram,
+            timeline,
+        }
+    }
+}
+
+impl Durability {
+    pub fn failure(&self) -> Option<String> {
+        if self.users_lost == 0 && self.wishes_missing == 0 && self.wishes_changed == 0 {
+            return None;
+        }
+        Some(format!(
+            "{} of {} users lost, {} of {} wishes missing, {} changed",
+            self.users_lost, self.users, self.wishes_missing, self.wishes, self.wishes_changed
+        ))
+    }
+}
+
+impl Overall {
+    pub fn new(samples: &[&Sample], elapsed: Duration) -> Self {
+        let mut latencies: Vec<Duration> = samples.iter().map(|s| s.latency).collect();
+        latencies.sort();
+        let count = samples.len() as u64;
+        let total: Duration = latencies.iter().sum();
+        let mut kinds: Vec<ErrorKind> = samples.iter().filter_map(|s| s.error).collect();
+        kinds.sort();
+        let mut errors_by_kind = BTreeMap::new();
+        for kind in &kinds {
+            *errors_by_kind.entry(kind.to_string()).or_insert(0) += 1;
+        }
+        Self {
+            count,
+            errors: kinds.len() as u64,
+            errors_by_kind,
+            throughput: if elapsed.is_zero() {
+                0.0
+            } else {
+                count as f64 / elapsed.as_secs_f64()
+            },
+            latency: LatencySummary {
+                min_ms: latencies.first().map(|l| as_ms(*l)).unwrap_or_default(),
+                mean_ms: if count == 0 {
+                    0.0
+                } else {
+                    as_ms(total) / count as f64
+                },
+                p50_ms: percentile(&latencies, 0.50),
+                p90_ms: percentile(&latencies, 0.90),
+                p95_ms: percentile(&latencies, 0.95),
+                p99_ms: percentile(&latencies, 0.99),
+                max_ms: latencies.last().map(|l| as_ms(*l)).unwrap_or_default(),
+            },
+        }
+    }
+}
+
+/// Index of the histogram bucket `latency` falls into.
+fn bucket_index(latency: Duration) -> usize {
+    let ms = as_ms(latency);
+    HISTOGRAM_BOUNDS_MS
+        .iter()
+        .position(|bound| ms <= *bound)
+        .unwrap_or(HISTOGRAM_BOUNDS_MS.len() - 1)
+}
+
+/// Nearest-rank percentile of already sorted latencies.
+fn percentile(sorted: &[Duration], q: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = ((q * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len());
+    as_ms(sorted[rank - 1])
+}
+
+/// Quote a CSV field if needed, doubling embedded quotes.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+pub fn as_ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(recorder: &Recorder, error: bool) -> Sample {
+        Sample {
+            operation: Operation::GetWishes,
+            start: recorder.offset(Instant::now()),
+            latency: Duration::from_millis(1),
+            status: Some(if error { 500 } else { 200 }),
+            bytes: 0,
+            error: error.then_some(ErrorKind::Status(500)),
+            message: None,
+        }
+    }
+
+    #[test]
+    fn setup_errors_do_not_abort_the_run() {
+        let recorder = Recorder::new().with_max_error_rate(Some(0.1));
+        for _ in 0..50 {
+            recorder.record(sample(&recorder, true));
+        }
+        assert!(!recorder.aborted());
+        recorder.start_measuring();
+        for _ in 0..MIN_REQUESTS_FOR_ABORT {
+            recorder.record(sample(&recorder, false));
+        }
+        assert!(!recorder.aborted());
+        assert_eq!(recorder.abort_reason(), None);
+    }
+
+    #[test]
+    fn measured_errors_abort_the_run() {
+        let recorder = Recorder::new().with_max_error_rate(Some(0.1));
+        recorder.start_measuring();
+        for index in 0..MIN_REQUESTS_FOR_ABORT {
+            recorder.record(sample(&recorder, index % 4 == 0));
+        }
+        assert!(recorder.aborted());
+        assert_eq!(
+            recorder.abort_reason().unwrap(),
+            "aborted after 5 of 20 measured requests failed, more than the allowed 10%"
+        );
+    }
+
+    fn ms(values: &[u64]) -> Vec<Duration> {
+        values.iter().map(|&ms| Duration::from_millis(ms)).collect()
+    }
+
+    #[test]
+    fn percentiles_use_the_nearest_rank() {
+        let sorted = ms(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+        assert_eq!(percentile(&sorted, 0.0), 1.0);
+        assert_eq!(percentile(&sorted, 0.5), 5.0);
+        assert_eq!(percentile(&sorted, 0.9), 9.0);
+        assert_eq!(percentile(&sorted, 0.95), 10.0);
+        assert_eq!(percentile(&sorted, 0.99), 10.0);
+        assert_eq!(percentile(&sorted, 1.0), 10.0);
+    }
+
+    #[test]
+    fn percentiles_of_few_samples() {
+        assert_eq!(percentile(&[], 0.5), 0.0);
+        assert_eq!(percentile(&ms(&[7]), 0.01), 7.0);
+        assert_eq!(percentile(&ms(&[7]), 0.99), 7.0);
+        assert_eq!(percentile(&ms(&[1, 100]), 0.5), 1.0);
+        assert_eq!(percentile(&ms(&[1, 100]), 0.51), 100.0);
+    }
+}
@@ -0,0 +1,64 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::Error;
+
+/// The directory all files of one run are collected in, e.g. `runs/1737800000-case-two`.
+pub struct RunDir {
+    path: PathBuf,
+}
+
+impl RunDir {
+    pub fn create(base: &Path, name: &str) -> Result<Self, Error> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        std::fs::create_dir_all(base)?;
+        // runs of a sweep can start within the same second
+        let mut attempt = 1;
+        loop {
+            let path = match attempt {
+                1 => base.join(format!("{}-{}", timestamp, name)),
+                _ => base.join(format!("{}-{}-{}", timestamp, name, attempt)),
+            };
+            match std::fs::create_dir(&path) {
+                Ok(()) => return Ok(Self { path }),
+                Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => attempt += 1,
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    /// A run directory that already exists, e.g. the one of `testing env up`.
+    pub fn open(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn file(&self, name: &str) -> PathBuf {
+        self.path.join(name)
+    }
+
+    /// Open a log file in the run directory, appending when a process is started again.
+    pub fn log(&self, name: &str) -> Result<File, Error> {
+        Ok(File::options()
+            .create(true)
+            .append(true)
+            .open(self.file(name))?)
+    }
+}
+
+/// The last `n` lines of a file, empty if it cannot be read.
+pub fn tail(path: &Path, n: usize) -> Vec<String> {
+    let Ok(file) = File::open(path) else {
+        return Vec::new();
+    };
+    let lines: Vec<String> = BufReader::new(file).lines().map_while(Result::ok).collect();
+    lines[lines.len().saturating_sub(n)..].to_vec()
+}
@@ -1,3 +1,5 @@
+use crate::error::Error;
+
 pub trait Runnable {
-    async fn run(&mut self) -> Result<(), reqwest::Error>;
+    async fn run(&mut self) -> Result<(), Error>;
 }
@@ -0,0 +1,57 @@
+use std::net::TcpStream;
+use std::path::Path;
+use std::process::{Child, Command};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::error::Error;
+use crate::run_dir::RunDir;
+
+pub const SERVER_LOG: &str = "server.log";
+/// Where the server listens, see `URL_PREFIX` of the cases.
+const ADDRESS: &str = "localhost:8080";
+/// How long the server may take to accept connections.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+/// How often we try to connect while the server starts.
+const STARTUP_POLL: Duration = Duration::from_millis(100);
+
+/// Start the wishes server against the PD at `pd_endpoint`, with its output in the log of `run_dir`,
+/// and wait until it accepts connections.
+pub fn start(executable: &Path, pd_endpoint: &str, run_dir: &RunDir) -> Result<Child, Error> {
+    let server_log = run_dir.log(SERVER_LOG)?;
+    let mut server = Command::new(executable)
+        .arg(pd_endpoint)
+        .stdout(server_log.try_clone()?)
+        .stderr(server_log)
+        .spawn()
+        .map_err(|err| {
+            Error::Invalid(format!(
+                "cannot start the server {}: {}",
+                executable.display(),
+                err
+            ))
+        })?;
+    let started = Instant::now();
+    while TcpStream::connect(ADDRESS).is_err() {
+        let failure = if server.try_wait()?.is_some() {
+            "stopped before accepting connections".to_owned()
+        } else if started.elapsed() > STARTUP_TIMEOUT {
+            let _ = server.kill();
+            let _ = server.wait();
+            format!(
+                "did not accept connections within {} s",
+                STARTUP_TIMEOUT.as_secs()
+            )
+        } else {
+            thread::sleep(STARTUP_POLL);
+            continue;
+        };
+        return Err(Error::Invalid(format!(
+            "the server {}, see {}",
+            failure,
+            run_dir.file(SERVER_LOG).display()
+        )));
+    }
+    println!("Successfully started SurrealDB server!");
+    Ok(server)
+}
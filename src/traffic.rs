@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::error::Error;
+use crate::results::Operation;
+
+/// One request and its response, a line of the traffic file of `case --record`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Exchange {
+    pub operation: Operation,
+    /// Offset of the request start from the start of the run.
+    pub start_ms: f64,
+    pub latency_ms: f64,
+    pub method: String,
+    /// Path and query, e.g. `/api/wishes?with_username=false`.
+    pub path: String,
+    pub headers: BTreeMap<String, String>,
+    pub body: Option<String>,
+    /// `None` if no response arrived.
+    pub status: Option<u16>,
+    pub response_headers: BTreeMap<String, String>,
+    pub response_body: Option<String>,
+}
+
+/// Appends the exchanges of a run to a JSON lines file as they complete.
+pub struct TrafficLog {
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl TrafficLog {
+    pub fn create(path: &Path) -> Result<Self, Error> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Ok(Self {
+            writer: Mutex::new(BufWriter::new(File::create(path)?)),
+        })
+    }
+
+    pub fn write(&self, exchange: &Exchange) {
+        let mut writer = self.writer.lock().expect("failed to lock the traffic log");
+        let written = serde_json::to_writer(&mut *writer, exchange)
+            .map_err(std::io::Error::from)
+            .and_then(|_| writeln!(writer));
+        if let Err(err) = written {
+            eprintln!("Failed to record traffic: {}", err);
+        }
+    }
+
+    pub fn flush(&self) -> Result<(), Error> {
+        self.writer
+            .lock()
+            .expect("failed to lock the traffic log")
+            .flush()?;
+        Ok(())
+    }
+}
+
+/// `ms` as a duration, `None` if it is negative, not a number or too large.
+pub fn as_duration(ms: f64) -> Option<Duration> {
+    Duration::try_from_secs_f64(ms / 1000.0).ok()
+}
+
+/// The exchanges of a traffic file in the order they were sent.
+pub fn load(path: &Path) -> Result<Vec<Exchange>, Error> {
+    let file = File::open(path)?;
+    let mut exchanges = Vec::new();
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let exchange = serde_json::from_str::<Exchange>(&line).map_err(|err| {
+            Error::Invalid(format!(
+                "line {} of {} is no recorded request: {}",
+                index + 1,
+                path.display(),
+                err
+            ))
+        })?;
+        if as_duration(exchange.start_ms).is_none() || as_duration(exchange.latency_ms).is_none() {
+            return Err(Error::Invalid(format!(
+                "line {} of {} has an invalid start_ms or latency_ms",
+                index + 1,
+                path.display()
+            )));
+        }
+        exchanges.push(exchange);
+    }
+    exchanges.sort_by(|a, b| a.start_ms.total_cmp(&b.start_ms));
+    Ok(exchanges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_valid_milliseconds_only() {
+        assert_eq!(as_duration(1500.0), Some(Duration::from_millis(1500)));
+        assert_eq!(as_duration(0.0), Some(Duration::ZERO));
+        assert_eq!(as_duration(-1.0), None);
+        assert_eq!(as_duration(f64::NAN), None);
+        assert_eq!(as_duration(1e300), None);
+    }
+}